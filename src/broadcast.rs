@@ -0,0 +1,226 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use superchain_client::futures::Stream;
+
+struct Shared<S: Stream> {
+    source: Pin<Box<S>>,
+    // Items not yet taken by each consumer, `None` once the consumer is dropped
+    queues: Vec<Option<VecDeque<S::Item>>>,
+    wakers: Vec<Option<Waker>>,
+    done: bool,
+}
+
+impl<S: Stream> Shared<S> {
+    fn wake_all(&mut self) {
+        for waker in self.wakers.iter_mut().filter_map(Option::take) {
+            waker.wake();
+        }
+    }
+}
+
+/// One of the consumers returned by [`broadcast`].
+pub struct Broadcast<S: Stream> {
+    index: usize,
+    shared: Arc<Mutex<Shared<S>>>,
+}
+
+/// Duplicates `stream` into `n` consumers that each receive every item, in order.
+///
+/// Whichever consumer is polled when its queue is empty pulls the next item from `stream` and
+/// queues a clone of it for every other live consumer, so no extra task has to drive the source.
+/// Queues are unbounded: a consumer that falls behind buffers everything the others have already
+/// taken.
+pub fn broadcast<S>(stream: S, n: usize) -> Vec<Broadcast<S>>
+where
+    S: Stream,
+    S::Item: Clone,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        source: Box::pin(stream),
+        queues: (0..n).map(|_| Some(VecDeque::new())).collect(),
+        wakers: (0..n).map(|_| None).collect(),
+        done: false,
+    }));
+    (0..n)
+        .map(|index| Broadcast {
+            index,
+            shared: Arc::clone(&shared),
+        })
+        .collect()
+}
+
+impl<S> Stream for Broadcast<S>
+where
+    S: Stream,
+    S::Item: Clone,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        let shared = &mut *shared;
+
+        if let Some(item) = shared.queues[self.index]
+            .as_mut()
+            .and_then(VecDeque::pop_front)
+        {
+            return Poll::Ready(Some(item));
+        }
+        if shared.done {
+            return Poll::Ready(None);
+        }
+
+        match shared.source.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                let others = shared
+                    .queues
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(index, _)| *index != self.index)
+                    .filter_map(|(_, queue)| queue.as_mut());
+                for queue in others {
+                    queue.push_back(item.clone());
+                }
+                shared.wake_all();
+                Poll::Ready(Some(item))
+            }
+            Poll::Ready(None) => {
+                shared.done = true;
+                shared.wake_all();
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                // The source only wakes the last consumer that polled it, which then wakes the rest
+                shared.wakers[self.index] = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<S: Stream> Drop for Broadcast<S> {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.queues[self.index] = None;
+            shared.wakers[self.index] = None;
+            // This consumer may be the one the source would have woken, so let the others poll it
+            shared.wake_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use superchain_client::futures::{
+        channel::mpsc,
+        executor::block_on,
+        future::join3,
+        stream,
+        task::{noop_waker, waker, ArcWake},
+        StreamExt,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct WakeFlag(AtomicBool);
+
+    impl ArcWake for WakeFlag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl WakeFlag {
+        fn take(&self) -> bool {
+            self.0.swap(false, Ordering::SeqCst)
+        }
+    }
+
+    fn poll<S: Stream + Unpin>(stream: &mut S, waker: &Waker) -> Poll<Option<S::Item>> {
+        Pin::new(stream).poll_next(&mut Context::from_waker(waker))
+    }
+
+    #[test]
+    fn every_consumer_gets_every_item_in_order() {
+        let mut consumers = broadcast(stream::iter(0..100), 3);
+        let (c, b, a) = (
+            consumers.pop().unwrap(),
+            consumers.pop().unwrap(),
+            consumers.pop().unwrap(),
+        );
+        let (a, b, c) = block_on(join3(
+            a.collect::<Vec<_>>(),
+            b.collect::<Vec<_>>(),
+            c.collect::<Vec<_>>(),
+        ));
+        let expected = (0..100).collect::<Vec<_>>();
+        assert_eq!((a, b, c), (expected.clone(), expected.clone(), expected));
+    }
+
+    #[test]
+    fn consumer_left_behind_buffers_what_it_missed() {
+        let mut consumers = broadcast(stream::iter(0..5), 2);
+        let late = consumers.pop().unwrap();
+        let eager = consumers.pop().unwrap();
+        assert_eq!(block_on(eager.collect::<Vec<_>>()), vec![0, 1, 2, 3, 4]);
+        assert_eq!(block_on(late.collect::<Vec<_>>()), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn dropping_the_consumer_holding_the_source_waker_wakes_the_others() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut consumers = broadcast(rx, 2);
+        let mut last = consumers.pop().unwrap();
+        let mut first = consumers.pop().unwrap();
+        let flag = Arc::new(WakeFlag::default());
+        let first_waker = waker(Arc::clone(&flag));
+
+        assert!(poll(&mut first, &first_waker).is_pending());
+        // The channel only keeps the waker of the last consumer to poll it
+        assert!(poll(&mut last, &noop_waker()).is_pending());
+        assert!(!flag.take());
+
+        drop(last);
+        assert!(flag.take());
+        assert!(poll(&mut first, &first_waker).is_pending());
+        tx.unbounded_send(7).unwrap();
+        assert!(flag.take());
+        assert_eq!(poll(&mut first, &first_waker), Poll::Ready(Some(7)));
+    }
+
+    #[test]
+    fn end_of_stream_wakes_every_consumer() {
+        let (tx, rx) = mpsc::unbounded::<u32>();
+        let mut consumers = broadcast(rx, 2);
+        let mut last = consumers.pop().unwrap();
+        let mut first = consumers.pop().unwrap();
+        let flag = Arc::new(WakeFlag::default());
+        let first_waker = waker(Arc::clone(&flag));
+
+        assert!(poll(&mut first, &first_waker).is_pending());
+        assert!(poll(&mut last, &noop_waker()).is_pending());
+        drop(tx);
+
+        assert_eq!(poll(&mut last, &noop_waker()), Poll::Ready(None));
+        assert!(flag.take());
+        assert_eq!(poll(&mut first, &first_waker), Poll::Ready(None));
+    }
+
+    #[test]
+    fn source_is_dropped_once_every_consumer_is_gone() {
+        let (tx, rx) = mpsc::unbounded::<u32>();
+        let consumers = broadcast(rx, 3);
+        assert!(!tx.is_closed());
+        drop(consumers);
+        assert!(tx.is_closed());
+        assert!(tx.unbounded_send(1).is_err());
+    }
+}
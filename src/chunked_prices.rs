@@ -0,0 +1,189 @@
+use std::pin::Pin;
+
+use superchain_client::{
+    ethers::types::H160,
+    futures::{stream, Stream, StreamExt, TryStreamExt},
+    Price, WsClient,
+};
+
+use crate::resume::resume_on_error;
+
+const RETRIES: usize = 1;
+
+/// Splits the inclusive block range into consecutive inclusive chunks of at most `chunk_size`.
+fn chunk_ranges(from: u64, to_inclusive: u64, chunk_size: u64) -> impl Iterator<Item = (u64, u64)> {
+    assert!(chunk_size > 0, "chunk_size must be positive");
+    (from..=to_inclusive)
+        .step_by(usize::try_from(chunk_size).unwrap())
+        .map(move |start| {
+            (
+                start,
+                start.saturating_add(chunk_size - 1).min(to_inclusive),
+            )
+        })
+}
+
+/// Streams the prices of one chunk, requesting the chunk again from its start if the request or
+/// the stream fails part way.
+fn get_chunk(
+    client: &WsClient,
+    token: H160,
+    from: u64,
+    to_inclusive: u64,
+) -> impl Stream<Item = anyhow::Result<Price>> + '_ {
+    resume_on_error(
+        move || async move {
+            let prices = client
+                .get_prices([token], Some(from), Some(to_inclusive))
+                .await?;
+            Ok(prices.map_err(anyhow::Error::from))
+        },
+        RETRIES,
+        || std::future::ready(()),
+    )
+}
+
+/// Concatenates the streams `get_chunk` returns for each of `ranges`, ending after the first error.
+///
+/// A chunk is only requested once the previous one is exhausted, so none is requested after an
+/// error.
+fn concat_until_error<T, I, F, S>(ranges: I, get_chunk: F) -> impl Stream<Item = anyhow::Result<T>>
+where
+    I: Iterator<Item = (u64, u64)>,
+    F: FnMut((u64, u64)) -> S,
+    S: Stream<Item = anyhow::Result<T>>,
+{
+    let chunks = ranges.map(get_chunk);
+    stream::unfold(
+        (chunks, None::<Pin<Box<S>>>, false),
+        |(mut chunks, mut chunk, failed)| async move {
+            if failed {
+                return None;
+            }
+            loop {
+                let current = match &mut chunk {
+                    Some(current) => current,
+                    None => chunk.insert(Box::pin(chunks.next()?)),
+                };
+                match current.next().await {
+                    Some(item) => {
+                        let failed = item.is_err();
+                        return Some((item, (chunks, chunk, failed)));
+                    }
+                    None => chunk = None,
+                }
+            }
+        },
+    )
+}
+
+/// Fetches the prices of `token` between the two blocks (inclusive) in chunks of `chunk_size`
+/// blocks, concatenated in block order.
+///
+/// Each chunk is only requested once the previous one is exhausted. A chunk whose request or
+/// stream fails is requested once more, skipping the prices it already gave; if that fails too,
+/// the error is emitted and the stream ends, rather than go on with a gap in the blocks.
+pub fn get_prices_chunked(
+    client: &WsClient,
+    token: H160,
+    from: u64,
+    to_inclusive: u64,
+    chunk_size: u64,
+) -> impl Stream<Item = anyhow::Result<Price>> + '_ {
+    concat_until_error(
+        chunk_ranges(from, to_inclusive, chunk_size),
+        move |(chunk_from, chunk_to)| get_chunk(client, token, chunk_from, chunk_to),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+
+    /// Checks that the chunks cover `from..=to_inclusive` exactly, in order.
+    fn assert_partition(from: u64, to_inclusive: u64, chunk_size: u64) {
+        let chunks = chunk_ranges(from, to_inclusive, chunk_size).collect::<Vec<_>>();
+        let mut next = from;
+        for &(start, end) in &chunks {
+            assert_eq!(start, next, "gap or overlap before {start} in {chunks:?}");
+            assert!(
+                start <= end && end - start < chunk_size,
+                "bad chunk in {chunks:?}"
+            );
+            next = end + 1;
+        }
+        assert_eq!(next, to_inclusive + 1, "range not covered by {chunks:?}");
+    }
+
+    #[test]
+    fn chunks_partition_the_range() {
+        assert_partition(100, 199, 10);
+        assert_partition(100, 205, 10);
+        assert_partition(15_500_000, 15_600_000, 7_777);
+        assert_eq!(
+            chunk_ranges(10, 24, 5).collect::<Vec<_>>(),
+            vec![(10, 14), (15, 19), (20, 24)]
+        );
+    }
+
+    #[test]
+    fn chunk_larger_than_range_is_one_chunk() {
+        assert_eq!(
+            chunk_ranges(10, 14, 100).collect::<Vec<_>>(),
+            vec![(10, 14)]
+        );
+    }
+
+    #[test]
+    fn single_block_range_is_one_chunk() {
+        assert_eq!(chunk_ranges(7, 7, 1).collect::<Vec<_>>(), vec![(7, 7)]);
+        assert_eq!(chunk_ranges(7, 7, 10).collect::<Vec<_>>(), vec![(7, 7)]);
+    }
+
+    #[test]
+    fn range_ending_at_the_last_block_does_not_overflow() {
+        assert_eq!(
+            chunk_ranges(u64::MAX - 4, u64::MAX, 3).collect::<Vec<_>>(),
+            vec![(u64::MAX - 4, u64::MAX - 2), (u64::MAX - 1, u64::MAX)]
+        );
+    }
+
+    #[test]
+    fn no_chunk_is_requested_after_a_failed_one() {
+        let requested = RefCell::new(Vec::new());
+        let items = block_on(
+            concat_until_error(chunk_ranges(0, 19, 5), |(from, to)| {
+                requested.borrow_mut().push((from, to));
+                let chunk = if from == 5 {
+                    vec![Ok(from), Err(anyhow::anyhow!("retries exhausted"))]
+                } else {
+                    vec![Ok(from), Ok(to)]
+                };
+                stream::iter(chunk)
+            })
+            .map(|item| item.map_err(|err| err.to_string()))
+            .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            items,
+            vec![Ok(0), Ok(4), Ok(5), Err("retries exhausted".to_owned())]
+        );
+        assert_eq!(requested.into_inner(), vec![(0, 4), (5, 9)]);
+    }
+
+    #[test]
+    fn chunks_are_concatenated_in_order() {
+        let items = block_on(
+            concat_until_error(chunk_ranges(0, 9, 4), |(from, to)| {
+                stream::iter([Ok::<_, anyhow::Error>(from), Ok(to)])
+            })
+            .map(Result::unwrap)
+            .collect::<Vec<_>>(),
+        );
+        assert_eq!(items, vec![0, 3, 4, 7, 8, 9]);
+    }
+}
@@ -0,0 +1,155 @@
+use superchain_client::futures::{stream, Stream, StreamExt};
+
+use crate::{
+    returns::LogReturns,
+    util::{is_usable_price, RollingWindow},
+    Priced,
+};
+
+struct State<Q> {
+    price_streams: Vec<Q>,
+    log_returns: Vec<LogReturns>,
+    // One row of returns (one per asset) per observation
+    window: RollingWindow<Vec<f64>>,
+}
+
+impl<Q> State<Q> {
+    fn push(&mut self, returns: Vec<f64>) -> Option<Vec<Vec<f64>>> {
+        self.window.push(returns);
+
+        if !self.window.is_full() {
+            return None;
+        }
+        Some(correlation_matrix(&self.window))
+    }
+}
+
+fn correlation_matrix(window: &RollingWindow<Vec<f64>>) -> Vec<Vec<f64>> {
+    let assets = window.iter().next().map_or(0, Vec::len);
+    let count = window.len() as f64;
+    let means = (0..assets)
+        .map(|i| window.iter().map(|row| row[i]).sum::<f64>() / count)
+        .collect::<Vec<_>>();
+    let covariance = |i: usize, j: usize| {
+        window
+            .iter()
+            .map(|row| (row[i] - means[i]) * (row[j] - means[j]))
+            .sum::<f64>()
+            / count
+    };
+    let variances = (0..assets).map(|i| covariance(i, i)).collect::<Vec<_>>();
+
+    (0..assets)
+        .map(|i| {
+            (0..assets)
+                .map(|j| {
+                    if i == j {
+                        1.0
+                    } else if variances[i] == 0.0 || variances[j] == 0.0 {
+                        0.0
+                    } else {
+                        covariance(i, j) / (variances[i] * variances[j]).sqrt()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Rolling `N x N` correlation matrix of the log returns of `N` price streams over the last
+/// `memory` observations.
+///
+/// The streams are assumed to be synchronized: one item is taken from each stream per step, and
+/// the output ends as soon as any of them ends. A step in which any price is zero, negative or
+/// non-finite is skipped for all of them. Nothing is emitted until `memory` steps of returns
+/// have been seen. The diagonal is always `1.0`, and an asset whose returns are flat over the
+/// window has a correlation of `0.0` with every other asset.
+pub fn correlation_matrix_stream<Q, P>(
+    price_streams: Vec<Q>,
+    memory: u32,
+) -> impl Stream<Item = Vec<Vec<f64>>> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State {
+        log_returns: price_streams.iter().map(|_| LogReturns::new()).collect(),
+        price_streams,
+        window: RollingWindow::new(usize::try_from(memory).unwrap()),
+    };
+    stream::unfold(init, |mut state| async move {
+        let mut prices = Vec::with_capacity(state.price_streams.len());
+        for price_stream in &mut state.price_streams {
+            prices.push(price_stream.next().await?.price());
+        }
+        // A step is skipped for every asset if any price in it is bad, so that the next row
+        // doesn't pair one-step returns with a return spanning the gap
+        if !prices.iter().copied().all(is_usable_price) {
+            return Some((None, state));
+        }
+
+        // Every asset has to see the price before the returns are combined
+        let returns = state
+            .log_returns
+            .iter_mut()
+            .zip(prices)
+            .map(|(log_returns, price)| log_returns.update(price))
+            .collect::<Vec<_>>();
+        let matrix = match returns.into_iter().collect::<Option<Vec<_>>>() {
+            Some(returns) => state.push(returns),
+            None => None,
+        };
+        Some((matrix, state))
+    })
+    .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::{price_stream, prices_from_log_returns};
+
+    #[test]
+    fn identical_assets_correlate_and_independent_one_does_not() {
+        let a = prices_from_log_returns([0.01, 0.01, -0.01, -0.01].repeat(4));
+        let a_scaled = a.iter().map(|p| p * 3.0).collect::<Vec<_>>();
+        let c = prices_from_log_returns([0.01, -0.01, 0.01, -0.01].repeat(4));
+        let streams = vec![price_stream(&a), price_stream(&a_scaled), price_stream(&c)];
+
+        let matrices = block_on(correlation_matrix_stream(streams, 8).collect::<Vec<_>>());
+        // 16 returns per asset, the first full window after 8
+        assert_eq!(matrices.len(), 9);
+        // Windows starting on a cycle boundary make a and c exactly uncorrelated
+        for matrix in matrices.iter().step_by(4) {
+            assert!(matrix.iter().enumerate().all(|(i, row)| row[i] == 1.0));
+            assert!((matrix[0][1] - 1.0).abs() < 1e-9, "{matrix:?}");
+            assert!(matrix[0][2].abs() < 1e-9, "{matrix:?}");
+            assert!(matrix[1][2].abs() < 1e-9, "{matrix:?}");
+            assert_eq!(matrix[0][2], matrix[2][0]);
+        }
+    }
+
+    #[test]
+    fn flat_asset_has_zero_correlation() {
+        let streams = vec![price_stream(&[1.0, 2.0, 1.5, 3.0]), price_stream(&[5.0; 4])];
+        let matrices = block_on(correlation_matrix_stream(streams, 3).collect::<Vec<_>>());
+        assert_eq!(matrices, vec![vec![vec![1.0, 0.0], vec![0.0, 1.0]]]);
+    }
+
+    #[test]
+    fn bad_print_in_one_feed_skips_the_step_for_all() {
+        let a = prices_from_log_returns([0.01, 0.01, -0.01, -0.01].repeat(4));
+        let mut a_with_gap = a.clone();
+        a_with_gap[5] = f64::NAN;
+        let streams = vec![price_stream(&a), price_stream(&a_with_gap)];
+
+        let matrices = block_on(correlation_matrix_stream(streams, 8).collect::<Vec<_>>());
+        // The step with the bad print, and the return after it, are missing from both
+        assert_eq!(matrices.len(), 8);
+        for matrix in matrices {
+            assert!((matrix[0][1] - 1.0).abs() < 1e-9, "{matrix:?}");
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::Priced;
+
+pub(crate) struct Ema {
+    alpha: f64,
+    last_ema: Option<f64>,
+}
+
+impl Ema {
+    pub(crate) fn new(period: u32) -> Self {
+        // A period of 0 would give `alpha = 2`, overshooting every value
+        assert!(period > 0, "period must be positive");
+        Self {
+            alpha: 2.0 / (f64::from(period) + 1.0),
+            last_ema: None,
+        }
+    }
+
+    pub(crate) fn update(&mut self, value: f64) -> f64 {
+        let ema = match self.last_ema {
+            Some(last_ema) => self.alpha * value + (1.0 - self.alpha) * last_ema,
+            None => value,
+        };
+        self.last_ema = Some(ema);
+        ema
+    }
+}
+
+/// Exponential moving average of prices with `alpha = 2 / (period + 1)`.
+///
+/// The average is seeded with the first price. Panics if `period` is 0.
+pub fn ema_stream<Q, P>(price_stream: Q, period: u32) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = Ema::new(period);
+    price_stream.scan(init, |ema, priced| {
+        std::future::ready(Some(ema.update(priced.price())))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, StreamExt};
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    #[test]
+    fn constant_prices_give_flat_ema() {
+        let emas = block_on(ema_stream(price_stream(&[42.0; 20]), 5).collect::<Vec<_>>());
+        assert_eq!(emas, vec![42.0; 20]);
+    }
+
+    #[test]
+    #[should_panic(expected = "period must be positive")]
+    fn zero_period_is_rejected() {
+        let _ = ema_stream(price_stream(&[1.0, 2.0, 3.0, 4.0]), 0);
+    }
+
+    #[test]
+    fn ema_reacts_to_a_step_faster_than_sma() {
+        const PERIOD: usize = 10;
+        let prices = [[0.0; PERIOD], [1.0; PERIOD]].concat();
+        let emas = block_on(ema_stream(price_stream(&prices), PERIOD as u32).collect::<Vec<_>>());
+
+        // The SMA catches up by the end of its window, so only the first bars after the step count
+        for i in PERIOD..PERIOD + PERIOD / 2 {
+            let sma = prices[i + 1 - PERIOD..=i].iter().sum::<f64>() / PERIOD as f64;
+            assert!(emas[i] > sma, "at {i}: ema {} <= sma {sma}", emas[i]);
+        }
+    }
+}
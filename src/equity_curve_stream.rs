@@ -0,0 +1,128 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{util::is_usable_price, Priced, Timestamped};
+
+struct State {
+    last_price: Option<f64>,
+    growth: f64,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            last_price: None,
+            growth: 1.0,
+        }
+    }
+}
+
+/// Cumulative growth of one unit invested at the first price, i.e. the running product of
+/// `1 + simple return`, starting at `1.0`.
+///
+/// Non-positive and non-finite prices are skipped.
+pub fn equity_curve_stream<Q, P>(price_stream: Q) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State::new();
+    price_stream
+        .scan(init, |state, priced| {
+            let price = priced.price();
+            if !is_usable_price(price) {
+                return std::future::ready(Some(None));
+            }
+
+            if let Some(last_price) = state.last_price {
+                state.growth *= 1.0 + (price - last_price) / last_price;
+            }
+            state.last_price = Some(price);
+
+            std::future::ready(Some(Some(state.growth)))
+        })
+        .filter_map(std::future::ready)
+}
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+/// Compound annual growth rate `(end / start)^(1 / years) - 1`, so doubling over a year is `1.0`.
+pub fn annualized_return(start_value: f64, end_value: f64, years: f64) -> f64 {
+    (end_value / start_value).powf(1.0 / years) - 1.0
+}
+
+/// Annualized return from the first price to each later one, using the items' timestamps for the
+/// elapsed time (in years of 365.25 days).
+///
+/// Items no later than the first one are skipped, as no rate can be given over no time. Like in
+/// [`equity_curve_stream`], non-positive and non-finite prices are skipped as well.
+pub fn annualized_return_stream<Q, P>(price_stream: Q) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + Timestamped + 'static,
+{
+    let mut first = None::<(f64, f64)>;
+    price_stream.filter_map(move |priced| {
+        let (price, timestamp) = (priced.price(), priced.timestamp());
+        let annualized = if !is_usable_price(price) {
+            None
+        } else if let Some((first_price, first_timestamp)) = first {
+            let years = (timestamp - first_timestamp) / SECONDS_PER_YEAR;
+            (years > 0.0).then(|| annualized_return(first_price, price, years))
+        } else {
+            first = Some((price, timestamp));
+            None
+        };
+        std::future::ready(annualized)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, stream};
+
+    use super::*;
+    use crate::test_util::{price_stream, TestPrice};
+
+    #[test]
+    fn up_ten_then_down_ten_ends_at_099() {
+        let curve =
+            block_on(equity_curve_stream(price_stream(&[100.0, 110.0, 99.0])).collect::<Vec<_>>());
+        assert_eq!(curve.len(), 3);
+        assert_eq!(curve[0], 1.0);
+        assert!((curve[1] - 1.1).abs() < 1e-12);
+        assert!((curve[2] - 0.99).abs() < 1e-12, "{}", curve[2]);
+    }
+
+    #[test]
+    fn unusable_prices_are_skipped() {
+        let curve = block_on(
+            equity_curve_stream(price_stream(&[0.0, 50.0, f64::NAN, 75.0])).collect::<Vec<_>>(),
+        );
+        assert_eq!(curve, vec![1.0, 1.5]);
+    }
+
+    #[test]
+    fn doubling_over_a_year_is_100_percent() {
+        assert!((annualized_return(100.0, 200.0, 1.0) - 1.0).abs() < 1e-12);
+        // Sub-year periods compound up: doubling in half a year is quadrupling in a year
+        assert!((annualized_return(100.0, 200.0, 0.5) - 3.0).abs() < 1e-12);
+        assert!((annualized_return(100.0, 121.0, 2.0) - 0.1).abs() < 1e-12);
+        assert_eq!(annualized_return(50.0, 50.0, 0.25), 0.0);
+    }
+
+    #[test]
+    fn stream_annualizes_over_elapsed_time() {
+        let prices =
+            [(0.0, 100.0), (0.0, 150.0), (0.5, 200.0), (1.0, 200.0)].map(|(years, price)| {
+                TestPrice {
+                    timestamp: 1_600_000_000.0 + years * SECONDS_PER_YEAR,
+                    price,
+                }
+            });
+        let annualized =
+            block_on(annualized_return_stream(stream::iter(prices)).collect::<Vec<_>>());
+        assert_eq!(annualized.len(), 2);
+        assert!((annualized[0] - 3.0).abs() < 1e-9, "{}", annualized[0]);
+        assert!((annualized[1] - 1.0).abs() < 1e-9, "{}", annualized[1]);
+    }
+}
@@ -0,0 +1,80 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{Priced, Timestamped};
+
+/// Flips a quote to the other side of its pair, e.g. USDC in WETH to WETH in USDC.
+///
+/// A zero price has no inverse and gives `f64::NAN`, so it isn't mistaken for a real (infinite)
+/// price downstream.
+pub fn invert_quote(price: f64) -> f64 {
+    if price == 0.0 {
+        f64::NAN
+    } else {
+        1.0 / price
+    }
+}
+
+/// A priced item quoted the other way around.
+#[derive(Debug, Clone)]
+pub struct Inverted<P>(pub P);
+
+impl<P: Priced> Priced for Inverted<P> {
+    fn price(&self) -> f64 {
+        invert_quote(self.0.price())
+    }
+}
+
+impl<P: Timestamped> Timestamped for Inverted<P> {
+    fn timestamp(&self) -> f64 {
+        self.0.timestamp()
+    }
+}
+
+/// Quotes every item of the stream the other way around, see [`invert_quote`].
+pub fn invert_stream<Q, P>(price_stream: Q) -> impl Stream<Item = Inverted<P>>
+where
+    Q: Stream<Item = P>,
+    P: Priced,
+{
+    price_stream.map(Inverted)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    #[test]
+    fn inverting_twice_gives_the_price_back() {
+        for price in [1.0, 0.000_625, 1600.0, 3.0, -2.5] {
+            let twice = invert_quote(invert_quote(price));
+            assert!(
+                (twice - price).abs() <= f64::EPSILON * price.abs(),
+                "{price} -> {twice}"
+            );
+        }
+    }
+
+    #[test]
+    fn zero_price_has_no_inverse() {
+        assert!(invert_quote(0.0).is_nan());
+        assert!(invert_quote(-0.0).is_nan());
+    }
+
+    #[test]
+    fn stream_keeps_timestamps_and_inverts_prices() {
+        let inverted = block_on(invert_stream(price_stream(&[4.0, 0.0, 0.5])).collect::<Vec<_>>());
+        assert_eq!(
+            inverted
+                .iter()
+                .map(Timestamped::timestamp)
+                .collect::<Vec<_>>(),
+            vec![0.0, 1.0, 2.0]
+        );
+        assert_eq!(inverted[0].price(), 0.25);
+        assert!(inverted[1].price().is_nan());
+        assert_eq!(inverted[2].price(), 2.0);
+    }
+}
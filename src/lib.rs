@@ -0,0 +1,86 @@
+mod broadcast;
+mod chunked_prices;
+mod correlation_stream;
+mod ema_stream;
+mod equity_curve_stream;
+mod invert_stream;
+mod lttb;
+mod macd_stream;
+mod merge_stream;
+mod moments_stream;
+mod quantile_stream;
+mod realized_skew_stream;
+mod reconnecting_client;
+mod regime_stream;
+mod resume;
+mod returns;
+mod sample_stream;
+mod sortino_stream;
+mod spike_filter_stream;
+mod spread_stream;
+#[cfg(test)]
+mod test_util;
+mod util;
+mod var_stream;
+mod vol_cone;
+mod vol_of_vol_stream;
+mod volatility_stream;
+mod vrp_stream;
+mod zscore_stream;
+
+use superchain_client::Price;
+
+pub use broadcast::{broadcast, Broadcast};
+pub use chunked_prices::get_prices_chunked;
+pub use correlation_stream::correlation_matrix_stream;
+pub use ema_stream::ema_stream;
+pub use equity_curve_stream::{annualized_return, annualized_return_stream, equity_curve_stream};
+pub use invert_stream::{invert_quote, invert_stream, Inverted};
+pub use lttb::{lttb, LttbDownsampler};
+pub use macd_stream::{macd_stream, Macd, MacdPeriods};
+pub use merge_stream::merge_by_timestamp;
+pub use moments_stream::{moments_stream, Moments};
+pub use quantile_stream::rolling_quantile_stream;
+pub use realized_skew_stream::realized_skew_stream;
+pub use reconnecting_client::ReconnectingWsClient;
+pub use regime_stream::{regime_change_stream, RegimeEvent};
+pub use sample_stream::{sample_every, sample_every_n};
+pub use sortino_stream::sortino_stream;
+pub use spike_filter_stream::{spike_filter_stream, spike_tag_stream};
+pub use spread_stream::{spread_stream, Spread};
+pub use util::RollingWindow;
+pub use var_stream::var_stream;
+pub use vol_cone::{vol_cone, VolConeRow};
+pub use vol_of_vol_stream::vol_of_vol_stream;
+pub use volatility_stream::{volatility_from_returns, volatility_stream};
+pub use vrp_stream::vrp_stream;
+pub use zscore_stream::zscore_stream;
+
+pub trait Priced {
+    fn price(&self) -> f64;
+}
+
+impl Priced for Price {
+    fn price(&self) -> f64 {
+        self.price
+    }
+}
+
+pub trait Timestamped {
+    /// Unix timestamp in seconds
+    fn timestamp(&self) -> f64;
+}
+
+impl Timestamped for Price {
+    fn timestamp(&self) -> f64 {
+        self.timestamp as f64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Volatility<T: Priced> {
+    _priced: T,
+    pub variance: f64,
+    /// Standard deviation, i.e. the square root of `variance`
+    pub value: f64,
+}
@@ -0,0 +1,225 @@
+/// Downsamples `data` to `threshold` points with Largest-Triangle-Three-Buckets, which keeps the
+/// visual shape of the series (peaks and troughs included) much better than taking every n-th
+/// point.
+///
+/// `data` is expected to be sorted by x. The first and last points are always kept. If `data`
+/// already has at most `threshold` points, or `threshold` is below 3, it is returned as is. For a
+/// series too long to hold in memory, use [`LttbDownsampler`] instead.
+pub fn lttb(data: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= data.len() || threshold < 3 {
+        return data.to_vec();
+    }
+
+    // Every point but the first and last goes into one of `threshold - 2` buckets
+    let bucket_size = (data.len() - 2) as f64 / (threshold - 2) as f64;
+    let bucket_start = |bucket: usize| (bucket as f64 * bucket_size) as usize + 1;
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(data[0]);
+    let mut selected = data[0];
+
+    for bucket in 0..threshold - 2 {
+        // The third corner of the triangles is the average of the next bucket (or the last point)
+        let next = &data[bucket_start(bucket + 1)..bucket_start(bucket + 2).min(data.len())];
+        let count = next.len() as f64;
+        let (next_x, next_y) = next
+            .iter()
+            .fold((0.0, 0.0), |(x, y), point| (x + point.0, y + point.1));
+        let (next_x, next_y) = (next_x / count, next_y / count);
+
+        let (x, y) = selected;
+        selected = data[bucket_start(bucket)..bucket_start(bucket + 1)]
+            .iter()
+            .copied()
+            .max_by(|a, b| {
+                let area =
+                    |p: &(f64, f64)| ((x - next_x) * (p.1 - y) - (x - p.0) * (next_y - y)).abs();
+                area(a).total_cmp(&area(b))
+            })
+            .unwrap();
+        sampled.push(selected);
+    }
+
+    sampled.push(data[data.len() - 1]);
+    sampled
+}
+
+/// Highest and lowest point of one bucket
+#[derive(Clone, Copy)]
+struct Bucket {
+    index: u64,
+    low: (f64, f64),
+    high: (f64, f64),
+}
+
+impl Bucket {
+    fn add(&mut self, point: (f64, f64)) {
+        if point.1 < self.low.1 {
+            self.low = point;
+        }
+        if point.1 > self.high.1 {
+            self.high = point;
+        }
+    }
+}
+
+/// Downsamples a series that arrives point by point to `threshold` points, holding at most
+/// `4 * threshold + 2` of them however long the series gets.
+///
+/// The points between the first and the last go into buckets of equal width in x, starting at one
+/// unit of x, of which only the highest and lowest point are kept. Whenever there are more than
+/// `2 * threshold` buckets, the width doubles and neighbouring buckets are merged. [`lttb`] then
+/// picks the final points from what is left, so the peaks and troughs survive.
+pub struct LttbDownsampler {
+    threshold: usize,
+    width: f64,
+    first: Option<(f64, f64)>,
+    last: Option<(f64, f64)>,
+    buckets: Vec<Bucket>,
+}
+
+impl LttbDownsampler {
+    pub fn new(threshold: usize) -> Self {
+        assert!(threshold >= 3, "threshold must be at least 3");
+        Self {
+            threshold,
+            width: 1.0,
+            first: None,
+            last: None,
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Adds the next point, which must not have a smaller x than the ones before it. Points with a
+    /// non-finite coordinate are skipped.
+    pub fn push(&mut self, point: (f64, f64)) {
+        if !(point.0.is_finite() && point.1.is_finite()) {
+            return;
+        }
+        let Some(first) = self.first else {
+            self.first = Some(point);
+            return;
+        };
+        // The point before this one is no longer the last, so it goes into a bucket
+        let Some(previous) = self.last.replace(point) else {
+            return;
+        };
+        let index = ((previous.0 - first.0) / self.width) as u64;
+        match self.buckets.last_mut() {
+            Some(bucket) if bucket.index == index => bucket.add(previous),
+            _ => self.buckets.push(Bucket {
+                index,
+                low: previous,
+                high: previous,
+            }),
+        }
+        while self.buckets.len() > 2 * self.threshold {
+            self.widen();
+        }
+    }
+
+    /// Doubles the bucket width, merging the buckets that now cover the same range.
+    fn widen(&mut self) {
+        self.width *= 2.0;
+        let mut merged = Vec::<Bucket>::with_capacity(self.buckets.len());
+        for mut bucket in self.buckets.drain(..) {
+            bucket.index /= 2;
+            match merged.last_mut() {
+                Some(last) if last.index == bucket.index => {
+                    last.add(bucket.low);
+                    last.add(bucket.high);
+                }
+                _ => merged.push(bucket),
+            }
+        }
+        self.buckets = merged;
+    }
+
+    /// Returns the downsampled series, which keeps the first and last point.
+    pub fn finish(self) -> Vec<(f64, f64)> {
+        let mut points = Vec::with_capacity(2 * self.buckets.len() + 2);
+        points.extend(self.first);
+        for Bucket { low, high, .. } in self.buckets {
+            let (left, right) = if low.0 <= high.0 {
+                (low, high)
+            } else {
+                (high, low)
+            };
+            points.push(left);
+            if right != left {
+                points.push(right);
+            }
+        }
+        points.extend(self.last);
+        lttb(&points, self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_target_count_ends_and_extremes() {
+        let mut data = (0..10_000)
+            .map(|i| {
+                let x = f64::from(i);
+                (x, (x / 300.0).sin())
+            })
+            .collect::<Vec<_>>();
+        data[4_321].1 = 5.0;
+        data[7_654].1 = -5.0;
+
+        let sampled = lttb(&data, 200);
+        assert_eq!(sampled.len(), 200);
+        assert_eq!(sampled.first(), data.first());
+        assert_eq!(sampled.last(), data.last());
+        assert!(sampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(sampled.contains(&data[4_321]));
+        assert!(sampled.contains(&data[7_654]));
+    }
+
+    #[test]
+    fn short_series_is_returned_as_is() {
+        let data = [(0.0, 1.0), (1.0, 3.0), (2.0, 2.0)];
+        assert_eq!(lttb(&data, 3), data);
+        assert_eq!(lttb(&data, 10), data);
+        assert_eq!(lttb(&data, 2), data);
+    }
+
+    #[test]
+    fn downsampler_keeps_target_count_ends_and_extremes_in_bounded_memory() {
+        let mut downsampler = LttbDownsampler::new(200);
+        let point = |i: u32| {
+            let x = f64::from(i);
+            let y = match i {
+                432_100 => 5.0,
+                765_400 => -5.0,
+                _ => (x / 30_000.0).sin(),
+            };
+            (x, y)
+        };
+        for i in 0..1_000_000 {
+            downsampler.push(point(i));
+            assert!(downsampler.buckets.len() <= 400);
+        }
+
+        let sampled = downsampler.finish();
+        assert_eq!(sampled.len(), 200);
+        assert_eq!(sampled.first(), Some(&point(0)));
+        assert_eq!(sampled.last(), Some(&point(999_999)));
+        assert!(sampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(sampled.contains(&point(432_100)));
+        assert!(sampled.contains(&point(765_400)));
+    }
+
+    #[test]
+    fn downsampler_returns_a_short_series_as_is() {
+        let data = [(0.0, 1.0), (0.5, f64::NAN), (1.0, 3.0), (2.0, 2.0)];
+        let mut downsampler = LttbDownsampler::new(10);
+        for point in data {
+            downsampler.push(point);
+        }
+        assert_eq!(downsampler.finish(), [data[0], data[2], data[3]]);
+    }
+}
@@ -0,0 +1,94 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{ema_stream::Ema, Priced};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Macd {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacdPeriods {
+    pub fast: u32,
+    pub slow: u32,
+    pub signal: u32,
+}
+
+impl Default for MacdPeriods {
+    fn default() -> Self {
+        Self {
+            fast: 12,
+            slow: 26,
+            signal: 9,
+        }
+    }
+}
+
+struct State {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl State {
+    fn new(periods: MacdPeriods) -> Self {
+        Self {
+            fast: Ema::new(periods.fast),
+            slow: Ema::new(periods.slow),
+            signal: Ema::new(periods.signal),
+        }
+    }
+}
+
+/// MACD line (fast EMA - slow EMA of prices), its signal-line EMA and the histogram between
+/// the two.
+///
+/// Panics if any of the periods is 0.
+pub fn macd_stream<Q, P>(
+    price_stream: Q,
+    periods: MacdPeriods,
+) -> impl Stream<Item = Macd> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State::new(periods);
+    price_stream.scan(init, |state, priced| {
+        let price = priced.price();
+
+        let macd = state.fast.update(price) - state.slow.update(price);
+        let signal = state.signal.update(macd);
+
+        std::future::ready(Some(Macd {
+            macd,
+            signal,
+            histogram: macd - signal,
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, StreamExt};
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    #[test]
+    fn histogram_flips_sign_after_turning_point() {
+        const TOP: usize = 40;
+        let prices = (0..2 * TOP)
+            .map(|i| 100.0 + TOP as f64 - TOP.abs_diff(i) as f64)
+            .collect::<Vec<_>>();
+        let macds = block_on(
+            macd_stream(price_stream(&prices), MacdPeriods::default()).collect::<Vec<_>>(),
+        );
+
+        assert!(macds[1..=TOP].iter().all(|m| m.histogram > 0.0));
+        let flip = macds.iter().position(|m| m.histogram < 0.0).unwrap();
+        assert!((TOP + 1..TOP + 10).contains(&flip), "flipped at {flip}");
+        assert!(macds[flip..].iter().all(|m| m.histogram < 0.0));
+    }
+}
@@ -1,82 +1,206 @@
-mod volatility_stream;
+mod run_config;
 
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use black_skulls::{
+    broadcast, invert_quote, volatility_stream, LttbDownsampler, Priced, ReconnectingWsClient,
+    Timestamped,
+};
+use clap::Parser;
 use plotlib::repr::ContinuousRepresentation;
+use run_config::RunConfig;
 use superchain_client::{
     config,
-    ethers::types::H160,
-    futures::{self, Stream, StreamExt, TryStreamExt},
-    tokio_tungstenite::connect_async,
-    tungstenite::{client::IntoClientRequest, http::header::AUTHORIZATION},
-    Price, WsClient,
+    futures::{Stream, StreamExt},
 };
 
-use volatility_stream::volatility_stream;
-
 const URL: &str = "wss://beta.superchain.app/websocket";
-const USDC: H160 = H160([
-    180, 225, 109, 1, 104, 229, 45, 53, 202, 205, 44, 97, 133, 180, 66, 129, 236, 40, 201, 220,
-]);
-const FROM_BLOCK: Option<u64> = Some(15500000);
-const TO_BLOCK_INC: Option<u64> = Some(15600000);
-const FILENAME: &str = "vol.svg";
-
-pub trait Priced {
-    fn price(&self) -> f64;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const VOL_COLOURS: [&str; 5] = ["red", "orange", "green", "blue", "purple"];
+
+#[derive(Parser)]
+struct Args {
+    /// Read the run configuration from this TOML file; the flags below take precedence over it
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Also write the full price and volatility series to this CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+    /// Width of the graph in pixels [default: 1920]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+    /// Height of the graph in pixels [default: 1080]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    height: Option<u32>,
 }
 
-impl Priced for Price {
-    fn price(&self) -> f64 {
-        self.price
+impl Args {
+    fn into_run_config(self) -> anyhow::Result<RunConfig> {
+        let mut run_config = match &self.config {
+            Some(path) => RunConfig::load(path)?,
+            None => RunConfig::default(),
+        };
+        if let Some(csv) = self.csv {
+            run_config.csv = Some(csv);
+        }
+        if let Some(width) = self.width {
+            run_config.width = width;
+        }
+        if let Some(height) = self.height {
+            run_config.height = height;
+        }
+        run_config.validate()?;
+        Ok(run_config)
     }
 }
 
-pub struct Volatility<T: Priced> {
-    _priced: T,
-    value: f64,
+/// Number of points each series is cut down to for plotting
+const POINTS: usize = 2000;
+
+/// The series plotted for a run, each downsampled to [`POINTS`] points.
+struct Series {
+    swaps: usize,
+    prices: Vec<(f64, f64)>,
+    /// One volatility series per window
+    vols: Vec<Vec<(f64, f64)>>,
 }
 
-async fn timestamp<Q: Stream<Item = Price> + Unpin>(price_stream: Q, output: &mut Vec<f64>) -> () {
-    let output2 = price_stream
-        .map(|p| p.timestamp as f64)
-        .collect::<Vec<_>>()
-        .await;
-    *output = output2;
+/// Computes the price and the volatility for each of `windows` from `prices`, writing every row
+/// to `csv` if there is one.
+///
+/// The series are computed in step, one price at a time, so that every series sees every price.
+/// Only their downsampled points are kept, so memory doesn't grow with the number of prices.
+async fn collect_series<Q, P>(
+    prices: Q,
+    windows: &[u32],
+    mut csv: Option<&mut dyn Write>,
+) -> std::io::Result<Series>
+where
+    Q: Stream<Item = P> + 'static,
+    P: Priced + Timestamped + Clone + 'static,
+{
+    let mut consumers = broadcast(prices, windows.len() + 1);
+    let mut prices = consumers.pop().unwrap();
+    let mut vol_streams = windows
+        .iter()
+        .zip(consumers)
+        .map(|(&window, consumer)| Box::pin(volatility_stream(consumer, window)))
+        .collect::<Vec<_>>();
+
+    if let Some(csv) = &mut csv {
+        write_csv_header(csv, windows)?;
+    }
+    let mut price_points = LttbDownsampler::new(POINTS);
+    let mut vol_points = windows
+        .iter()
+        .map(|_| LttbDownsampler::new(POINTS))
+        .collect::<Vec<_>>();
+    let mut swaps = 0;
+    let mut vols = Vec::with_capacity(windows.len());
+    'prices: while let Some(priced) = prices.next().await {
+        let timestamp = priced.timestamp();
+        // We are used to WETH in USDC prices, not the other way around
+        let price = invert_quote(priced.price());
+        vols.clear();
+        for vol_stream in &mut vol_streams {
+            match vol_stream.next().await {
+                Some(vol) => vols.push(vol.value),
+                None => break 'prices,
+            }
+        }
+
+        price_points.push((timestamp, price));
+        for (points, &vol) in vol_points.iter_mut().zip(&vols) {
+            points.push((timestamp, vol));
+        }
+        if let Some(csv) = &mut csv {
+            write_csv_row(csv, timestamp, price, &vols)?;
+        }
+        swaps += 1;
+    }
+    if let Some(csv) = &mut csv {
+        csv.flush()?;
+    }
+
+    Ok(Series {
+        swaps,
+        prices: price_points.finish(),
+        vols: vol_points
+            .into_iter()
+            .map(LttbDownsampler::finish)
+            .collect(),
+    })
+}
+
+/// Ends the stream at its first error, which is left in `error` for the caller to return once the
+/// consumers are done, instead of carrying on as if the series were complete.
+fn until_error<T>(
+    stream: impl Stream<Item = anyhow::Result<T>>,
+    error: Arc<Mutex<Option<anyhow::Error>>>,
+) -> impl Stream<Item = T> {
+    stream.scan((), move |(), item| {
+        std::future::ready(match item {
+            Ok(item) => Some(item),
+            Err(err) => {
+                *error.lock().unwrap() = Some(err);
+                None
+            }
+        })
+    })
 }
 
-async fn price<Q: Stream<Item = Price> + Unpin + 'static>(
-    price_stream: Q,
-    output: &mut Vec<f64>,
-) -> () {
-    // We are used to WETH in USDC prices, not the other way around
-    let output2 = price_stream
-        .map(|p| 1.0 / p.price)
-        .collect::<Vec<_>>()
-        .await;
-    *output = output2;
+fn write_csv_header(mut writer: impl Write, windows: &[u32]) -> std::io::Result<()> {
+    write!(writer, "timestamp,price")?;
+    for window in windows {
+        write!(writer, ",vol{window}")?;
+    }
+    writeln!(writer)
 }
 
-async fn volatility<Q: Stream<Item = Price> + Unpin + 'static>(
-    price_stream: Q,
-    memory: u32,
-    output: &mut Vec<f64>,
-) -> () {
-    let vol_stream = volatility_stream(price_stream, memory);
-    futures::pin_mut!(vol_stream);
-    let output2 = vol_stream.map(|v| v.value).collect::<Vec<_>>().await;
-    *output = output2;
+fn write_csv_row(
+    mut writer: impl Write,
+    timestamp: f64,
+    price: f64,
+    vols: &[f64],
+) -> std::io::Result<()> {
+    write!(writer, "{timestamp},{price}")?;
+    for vol in vols {
+        write!(writer, ",{vol}")?;
+    }
+    writeln!(writer)
 }
 
-fn into_data<'t>(
-    timestamps: impl IntoIterator<Item = &'t f64>,
-    values: impl IntoIterator<Item = f64>,
-) -> Vec<(f64, f64)> {
-    const EVERY: usize = 10;
-    timestamps
+/// Largest value among `points`, or negative infinity if there are none.
+fn max_value<'p>(points: impl IntoIterator<Item = &'p (f64, f64)>) -> f64 {
+    points
         .into_iter()
-        .step_by(EVERY)
-        .cloned()
-        .zip(values.into_iter().step_by(EVERY))
-        .collect()
+        .map(|(_t, value)| *value)
+        .fold(f64::NEG_INFINITY, f64::max)
+}
+
+/// Upper end of a value axis starting at zero: `max`, unless that would leave the range empty or
+/// unbounded.
+fn padded_y_max(max: f64) -> f64 {
+    if max.is_finite() && max > 0.0 {
+        max
+    } else {
+        1e-9
+    }
+}
+
+/// Time axis covering `min..max` in seconds, widened by a second either way if it is empty.
+fn padded_x_range(min: f64, max: f64) -> (f64, f64) {
+    if max > min {
+        (min, max)
+    } else {
+        (min - 1.0, max + 1.0)
+    }
 }
 
 fn into_chart(
@@ -92,88 +216,213 @@ fn into_chart(
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let mut req = URL.into_client_request()?;
-    let config = config::Config::from_env();
-    req.headers_mut().append(
-        AUTHORIZATION,
-        config.get_basic_authorization_value().try_into()?,
-    );
+    let run_config = Args::parse().into_run_config()?;
 
-    let (websocket, _) = connect_async(req).await.unwrap();
-    let client = WsClient::new(websocket).await;
+    let client = ReconnectingWsClient::new(URL, &config::Config::from_env())
+        .with_backoff(|| tokio::time::sleep(RECONNECT_DELAY));
 
     let prices = client
-        .get_prices([USDC], FROM_BLOCK, TO_BLOCK_INC)
-        .await?
-        .map_err(anyhow::Error::from);
-
-    let (tx, rx) = async_channel::unbounded();
-
-    let mut timestamps = Vec::<f64>::new();
-    let mut plain_prices = Vec::<f64>::new();
-    let mut vol50 = Vec::<f64>::new();
-    let mut vol500 = Vec::<f64>::new();
-    let mut vol5000 = Vec::<f64>::new();
-    tokio_scoped::scope(|s| {
-        s.spawn(timestamp(rx.clone(), &mut timestamps));
-        s.spawn(price(rx.clone(), &mut plain_prices));
-        s.spawn(volatility(rx.clone(), 50, &mut vol50));
-        s.spawn(volatility(rx.clone(), 500, &mut vol500));
-        s.spawn(volatility(rx.clone(), 5000, &mut vol5000));
-        s.spawn(async move {
-            prices
-                .filter_map(|p_res| async { p_res.ok() })
-                .for_each(|p| async { tx.send(p).await.unwrap() })
-                .await
-        });
-    });
+        .get_prices(
+            [run_config.token],
+            run_config.from_block,
+            run_config.to_block_inc,
+        )
+        .await?;
 
-    println!("{} swaps received and processed", timestamps.len());
+    let mut csv = match &run_config.csv {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+    let error = Arc::new(Mutex::new(None));
+    let Series {
+        swaps,
+        prices: data_price,
+        vols: data_vols,
+    } = collect_series(
+        until_error(prices, Arc::clone(&error)),
+        &run_config.windows,
+        csv.as_mut().map(|csv| csv as &mut dyn Write),
+    )
+    .await?;
+
+    if let Some(err) = error.lock().unwrap().take() {
+        return Err(err.context(format!("price stream failed after {swaps} swaps")));
+    }
+    println!("{swaps} swaps received and processed");
+    if let Some(path) = &run_config.csv {
+        println!("Written {} to disk", path.display());
+    }
 
-    let max_price = plain_prices.iter().map(|p| *p as u64).max().unwrap() as f64;
+    anyhow::ensure!(swaps > 0, "no swaps between the requested blocks");
+
+    let max_price = max_value(&data_price);
     println!("The max price is {max_price}");
 
-    let data_price = into_data(&timestamps, plain_prices);
-    let data50 = into_data(&timestamps, vol50);
-    let data500 = into_data(&timestamps, vol500);
-    let data5000 = into_data(&timestamps, vol5000);
+    let max_vol = max_value(data_vols.iter().flatten());
+    println!("Max volatility: {max_vol}");
 
-    const QUANTIZER: f64 = 1_000_000.0;
-    let max_vol = data50
+    let (min_x, max_x) = data_price
         .iter()
-        .map(|(_t, v)| (*v * QUANTIZER) as u64)
-        .max()
-        .unwrap() as f64
-        / QUANTIZER;
-    println!("Max volatility: {max_vol}");
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (t, _p)| {
+            (min.min(*t), max.max(*t))
+        });
 
     let line_chart_price = into_chart(data_price, "black");
-    let line_chart50 = into_chart(data50, "red");
-    let line_chart500 = into_chart(data500, "orange");
-    let line_chart5000 = into_chart(data5000, "green");
 
-    let min_x = timestamps.iter().map(|t| *t as u64).min().unwrap() as f64;
-    let max_x = timestamps.iter().map(|t| *t as u64).max().unwrap() as f64;
+    // A single swap, or a flat series, would leave plotlib with an empty range
+    let (min_x, max_x) = padded_x_range(min_x, max_x);
     // would be nice to have the y axis on the right...
     let price_view = plotlib::view::ContinuousView::new()
         .x_range(min_x, max_x)
-        .y_range(0.0, max_price)
+        .y_range(0.0, padded_y_max(max_price))
         .add(line_chart_price);
-    let vol_view = plotlib::view::ContinuousView::new()
-        .x_range(min_x, max_x)
-        .y_range(0.0, max_vol)
-        .add(line_chart50)
-        .add(line_chart500)
-        .add(line_chart5000);
+    let vol_view = data_vols.into_iter().zip(VOL_COLOURS.iter().cycle()).fold(
+        plotlib::view::ContinuousView::new()
+            .x_range(min_x, max_x)
+            .y_range(0.0, padded_y_max(max_vol)),
+        |view, (data, colour)| view.add(into_chart(data, *colour)),
+    );
 
     plotlib::page::Page::empty()
-        .dimensions(1920, 1080)
+        .dimensions(run_config.width, run_config.height)
         .add_plot(&price_view)
         .add_plot(&vol_view)
-        .save(FILENAME)
+        .save(&run_config.output)
         .expect("msg");
 
-    println!("Written {FILENAME} to disk");
+    println!("Written {} to disk", run_config.output.display());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures;
+
+    use super::*;
+
+    #[test]
+    fn csv_has_header_and_one_row_per_price() {
+        let mut csv = Vec::new();
+        write_csv_header(&mut csv, &[50, 500, 5000]).unwrap();
+        write_csv_row(&mut csv, 1.0, 1500.0, &[0.0; 3]).unwrap();
+        write_csv_row(&mut csv, 2.0, 1510.0, &[0.1; 3]).unwrap();
+        write_csv_row(&mut csv, 3.0, 1490.0, &[0.2; 3]).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("timestamp,price,vol50,vol500,vol5000"));
+        assert_eq!(lines.next(), Some("1,1500,0,0,0"));
+        assert_eq!(lines.count(), 2);
+    }
+
+    #[test]
+    fn zero_dimensions_are_rejected() {
+        assert!(Args::try_parse_from(["black-skulls", "--width", "0"]).is_err());
+        assert!(Args::try_parse_from(["black-skulls", "--height", "0"]).is_err());
+        assert!(Args::try_parse_from(["black-skulls", "--width", "-5"]).is_err());
+
+        let args = Args::try_parse_from(["black-skulls", "--width", "800", "--height", "600"]);
+        let run_config = args.unwrap().into_run_config().unwrap();
+        assert_eq!((run_config.width, run_config.height), (800, 600));
+    }
+
+    #[test]
+    fn first_error_ends_the_stream_and_is_kept() {
+        let items = vec![Ok(1), Ok(2), Err(anyhow::anyhow!("dropped")), Ok(3)];
+        let error = Arc::new(Mutex::new(None));
+        let passed = futures::executor::block_on(
+            until_error(futures::stream::iter(items), Arc::clone(&error)).collect::<Vec<_>>(),
+        );
+        assert_eq!(passed, vec![1, 2]);
+        let error = error.lock().unwrap().take();
+        assert_eq!(error.map(|err| err.to_string()), Some("dropped".to_owned()));
+    }
+
+    #[test]
+    fn stream_without_errors_passes_through() {
+        let error = Arc::new(Mutex::new(None));
+        let items = futures::stream::iter((0..5).map(anyhow::Ok));
+        let passed =
+            futures::executor::block_on(until_error(items, Arc::clone(&error)).collect::<Vec<_>>());
+        assert_eq!(passed, vec![0, 1, 2, 3, 4]);
+        assert!(error.lock().unwrap().is_none());
+    }
+
+    #[derive(Clone)]
+    struct Swap {
+        timestamp: f64,
+        price: f64,
+    }
+
+    impl Priced for Swap {
+        fn price(&self) -> f64 {
+            self.price
+        }
+    }
+
+    impl Timestamped for Swap {
+        fn timestamp(&self) -> f64 {
+            self.timestamp
+        }
+    }
+
+    fn swaps(count: u32) -> impl Stream<Item = Swap> {
+        futures::stream::iter((0..count).map(|i| Swap {
+            timestamp: f64::from(i),
+            price: 1.0 / (1600.0 + f64::from(i % 7)),
+        }))
+    }
+
+    #[test]
+    fn every_series_sees_every_price() {
+        let mut csv = Vec::new();
+        let series =
+            futures::executor::block_on(collect_series(swaps(1000), &[5, 50, 500], Some(&mut csv)))
+                .unwrap();
+
+        assert_eq!(series.swaps, 1000);
+        let timestamps = (0..1000).map(f64::from).collect::<Vec<_>>();
+        let price_timestamps = series.prices.iter().map(|(t, _p)| *t).collect::<Vec<_>>();
+        assert_eq!(price_timestamps, timestamps);
+        assert_eq!(series.prices[3].1, 1603.0);
+        assert_eq!(series.vols.len(), 3);
+        assert!(series.vols.iter().all(|vol| vol.len() == 1000));
+        // The header and a row per price
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 1001);
+    }
+
+    #[test]
+    fn long_series_are_downsampled() {
+        let series =
+            futures::executor::block_on(collect_series(swaps(10_000), &[5, 50], None)).unwrap();
+        assert_eq!(series.swaps, 10_000);
+        assert_eq!(series.prices.len(), POINTS);
+        assert!(series.vols.iter().all(|vol| vol.len() == POINTS));
+    }
+
+    #[test]
+    fn sub_one_maximum_is_kept() {
+        let vols = [(0.0, 0.2), (1.0, 0.7), (2.0, 0.4)];
+        assert_eq!(max_value(&vols), 0.7);
+        assert_eq!(padded_y_max(max_value(&vols)), 0.7);
+        let stable_prices = [(0.0, 0.999_5), (1.0, 1.000_2)];
+        assert_eq!(padded_y_max(max_value(&stable_prices)), 1.000_2);
+    }
+
+    #[test]
+    fn zero_max_is_padded() {
+        assert_eq!(padded_y_max(0.0), 1e-9);
+        assert_eq!(padded_y_max(max_value(&[])), 1e-9);
+        assert_eq!(padded_y_max(f64::NAN), 1e-9);
+        assert_eq!(padded_y_max(f64::INFINITY), 1e-9);
+        assert_eq!(padded_y_max(0.25), 0.25);
+        assert_eq!(padded_y_max(1600.0), 1600.0);
+    }
+
+    #[test]
+    fn single_timestamp_gets_a_second_either_way() {
+        assert_eq!(padded_x_range(1.6e9, 1.6e9), (1.6e9 - 1.0, 1.6e9 + 1.0));
+        assert_eq!(padded_x_range(1.6e9, 1.6e9 + 600.0), (1.6e9, 1.6e9 + 600.0));
+    }
+}
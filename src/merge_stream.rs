@@ -0,0 +1,91 @@
+use superchain_client::futures::{stream, Stream, StreamExt};
+
+use crate::Timestamped;
+
+struct Source<S, T> {
+    stream: S,
+    head: Option<anyhow::Result<T>>,
+    done: bool,
+}
+
+fn sort_key<T: Timestamped>(item: &anyhow::Result<T>) -> f64 {
+    match item {
+        Ok(timestamped) => timestamped.timestamp(),
+        // Errors are passed through as soon as they are seen
+        Err(_) => f64::NEG_INFINITY,
+    }
+}
+
+/// Merges streams that are each ordered by timestamp into one stream in non-decreasing timestamp
+/// order, preferring the earlier source on ties.
+///
+/// One item is buffered per source, and an item is only emitted once every source that has not
+/// ended has an item buffered. The merge therefore runs at the pace of the slowest source: a source
+/// that stalls holds back the whole output, even if the others have items ready.
+pub fn merge_by_timestamp<S, T>(streams: Vec<S>) -> impl Stream<Item = anyhow::Result<T>>
+where
+    S: Stream<Item = anyhow::Result<T>> + Unpin,
+    T: Timestamped,
+{
+    let sources = streams
+        .into_iter()
+        .map(|stream| Source {
+            stream,
+            head: None,
+            done: false,
+        })
+        .collect::<Vec<_>>();
+
+    stream::unfold(sources, |mut sources| async move {
+        for source in sources.iter_mut().filter(|s| s.head.is_none() && !s.done) {
+            source.head = source.stream.next().await;
+            source.done = source.head.is_none();
+        }
+
+        let (index, _) = sources
+            .iter()
+            .enumerate()
+            .filter_map(|(index, source)| source.head.as_ref().map(|head| (index, head)))
+            .min_by(|(_, a), (_, b)| sort_key(a).total_cmp(&sort_key(b)))?;
+        let item = sources[index].head.take()?;
+
+        Some((item, sources))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, stream::BoxStream};
+
+    use super::*;
+    use crate::test_util::TestPrice;
+
+    fn at(timestamps: &[f64]) -> BoxStream<'static, anyhow::Result<TestPrice>> {
+        let prices = timestamps
+            .iter()
+            .map(|&timestamp| {
+                Ok(TestPrice {
+                    timestamp,
+                    price: 1.0,
+                })
+            })
+            .collect::<Vec<_>>();
+        stream::iter(prices).boxed()
+    }
+
+    #[test]
+    fn merges_two_ordered_streams_in_order() {
+        let merged = merge_by_timestamp(vec![at(&[1.0, 4.0, 5.0, 5.0]), at(&[2.0, 3.0, 5.0, 8.0])]);
+        let timestamps = block_on(merged.map(|p| p.unwrap().timestamp).collect::<Vec<_>>());
+        assert_eq!(timestamps, vec![1.0, 2.0, 3.0, 4.0, 5.0, 5.0, 5.0, 8.0]);
+    }
+
+    #[test]
+    fn errors_are_passed_through() {
+        let failing = stream::iter(vec![Err(anyhow::anyhow!("gone"))]);
+        let merged = merge_by_timestamp(vec![at(&[1.0, 2.0]), failing.boxed()]);
+        let items = block_on(merged.collect::<Vec<_>>());
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{returns::LogReturns, util::RollingWindow, Priced};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moments {
+    pub mean: f64,
+    pub variance: f64,
+    pub skew: f64,
+    pub kurtosis: f64,
+}
+
+fn add_powers(sums: &mut [f64; 4], value: f64, sign: f64) {
+    let mut power = 1.0;
+    for sum in sums {
+        power *= value;
+        *sum += sign * power;
+    }
+}
+
+struct State {
+    memory: usize,
+    log_returns: LogReturns,
+    window: RollingWindow<f64>,
+    // Sums of the first four powers of the returns in the window
+    sums: [f64; 4],
+    pushes_since_resync: usize,
+}
+
+impl State {
+    fn new(memory: u32) -> Self {
+        let memory = usize::try_from(memory).unwrap();
+        Self {
+            memory,
+            log_returns: LogReturns::new(),
+            window: RollingWindow::new(memory),
+            sums: [0.0; 4],
+            pushes_since_resync: 0,
+        }
+    }
+
+    fn push(&mut self, log_return: f64) -> Option<Moments> {
+        if let Some(evicted) = self.window.push(log_return) {
+            add_powers(&mut self.sums, evicted, -1.0);
+        }
+        add_powers(&mut self.sums, log_return, 1.0);
+
+        // Adding and removing values lets rounding errors pile up in the sums, so rebuild them
+        // from the window once it has been fully replaced
+        self.pushes_since_resync += 1;
+        if self.pushes_since_resync >= self.memory {
+            self.sums = [0.0; 4];
+            for &value in self.window.iter() {
+                add_powers(&mut self.sums, value, 1.0);
+            }
+            self.pushes_since_resync = 0;
+        }
+
+        if !self.window.is_full() {
+            return None;
+        }
+
+        let count = self.window.len() as f64;
+        let [raw1, raw2, raw3, raw4] = self.sums.map(|sum| sum / count);
+        let mean = raw1;
+        let variance = (raw2 - mean * mean).max(0.0);
+        let third = raw3 - 3.0 * mean * raw2 + 2.0 * mean.powi(3);
+        let fourth = raw4 - 4.0 * mean * raw3 + 6.0 * mean.powi(2) * raw2 - 3.0 * mean.powi(4);
+
+        let (skew, kurtosis) = if variance == 0.0 {
+            (0.0, 0.0)
+        } else {
+            (third / variance.powf(1.5), fourth / variance.powi(2) - 3.0)
+        };
+
+        Some(Moments {
+            mean,
+            variance,
+            skew,
+            kurtosis,
+        })
+    }
+}
+
+/// Rolling mean, variance, skewness and excess kurtosis of the log returns over the last `memory`
+/// returns.
+///
+/// Nothing is emitted until `memory` returns have been seen. A flat window has zero skew and
+/// kurtosis.
+pub fn moments_stream<Q, P>(price_stream: Q, memory: u32) -> impl Stream<Item = Moments> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State::new(memory);
+    price_stream
+        .scan(init, |state, priced| {
+            let moments = state
+                .log_returns
+                .update(priced.price())
+                .and_then(|log_return| state.push(log_return));
+            std::future::ready(Some(moments))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::{price_stream, prices_from_log_returns};
+
+    fn last_moments(prices: &[f64], memory: u32) -> Moments {
+        let moments = block_on(moments_stream(price_stream(prices), memory).collect::<Vec<_>>());
+        *moments.last().unwrap()
+    }
+
+    #[test]
+    fn symmetric_returns_have_no_skew() {
+        let prices =
+            prices_from_log_returns((0..100).map(|i| if i % 2 == 0 { 0.01 } else { -0.01 }));
+        let moments = last_moments(&prices, 20);
+        assert!(moments.mean.abs() < 1e-12);
+        assert!((moments.variance - 1e-4).abs() < 1e-12);
+        assert!(moments.skew.abs() < 1e-6, "{}", moments.skew);
+    }
+
+    #[test]
+    fn rare_large_gains_skew_right() {
+        // One return of 0.09 and nine of -0.01 per ten: skew 8/3, excess kurtosis 46/9
+        let prices =
+            prices_from_log_returns((0..100).map(|i| if i % 10 == 0 { 0.09 } else { -0.01 }));
+        let moments = last_moments(&prices, 50);
+        assert!(moments.mean.abs() < 1e-12);
+        assert!((moments.skew - 8.0 / 3.0).abs() < 1e-6, "{}", moments.skew);
+        assert!(
+            (moments.kurtosis - 46.0 / 9.0).abs() < 1e-6,
+            "{}",
+            moments.kurtosis
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::util::RollingWindow;
+
+/// Exact quantile of the last `memory` values, kept as a sorted copy of the window.
+pub(crate) struct RollingQuantile {
+    quantile: f64,
+    window: RollingWindow<f64>,
+    sorted: Vec<f64>,
+}
+
+impl RollingQuantile {
+    pub(crate) fn new(memory: u32, quantile: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "quantile must be within [0, 1]"
+        );
+        let memory = usize::try_from(memory).unwrap();
+        Self {
+            quantile,
+            window: RollingWindow::new(memory),
+            sorted: Vec::with_capacity(memory),
+        }
+    }
+
+    /// Adds `value` to the window and returns the quantile once the window is full.
+    pub(crate) fn push(&mut self, value: f64) -> Option<f64> {
+        if let Some(evicted) = self.window.push(value) {
+            let index = self
+                .sorted
+                .partition_point(|v| v.total_cmp(&evicted).is_lt());
+            self.sorted.remove(index);
+        }
+        let index = self.sorted.partition_point(|v| v.total_cmp(&value).is_lt());
+        self.sorted.insert(index, value);
+
+        self.current()
+    }
+
+    /// The quantile of the window as it is, if it is full.
+    pub(crate) fn current(&self) -> Option<f64> {
+        if !self.window.is_full() {
+            return None;
+        }
+        Some(quantile_of_sorted(&self.sorted, self.quantile))
+    }
+}
+
+/// Quantile of ascending, non-empty `sorted`, interpolating linearly between the two closest ranks.
+pub(crate) fn quantile_of_sorted(sorted: &[f64], quantile: f64) -> f64 {
+    let rank = quantile * (sorted.len() - 1) as f64;
+    let lower = sorted[rank.floor() as usize];
+    let upper = sorted[rank.ceil() as usize];
+    lower + (upper - lower) * rank.fract()
+}
+
+/// Rolling `quantile` (between 0 and 1) of the last `memory` values, e.g. of a volatility series.
+///
+/// Nothing is emitted until `memory` values have been seen. The window is kept sorted rather than
+/// estimated with two heaps or P², so the result is exact but each update shifts up to `memory`
+/// values: at `memory = 5000` that is a move of up to 40 KB per update and 80 KB held in total.
+pub fn rolling_quantile_stream<Q>(
+    value_stream: Q,
+    memory: u32,
+    quantile: f64,
+) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = f64> + 'static,
+{
+    let init = RollingQuantile::new(memory, quantile);
+    value_stream
+        .scan(init, |state, value| {
+            std::future::ready(Some(state.push(value)))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, stream};
+
+    use super::*;
+    use crate::test_util::uniform_values;
+
+    #[test]
+    fn matches_quantile_of_sorted_window() {
+        const MEMORY: usize = 7;
+        // Rounded to 64 levels, so that the windows have repeats
+        let values = uniform_values(200)
+            .into_iter()
+            .map(|value| (value * 64.0).floor() / 64.0)
+            .collect::<Vec<_>>();
+        for quantile in [0.0, 0.05, 0.25, 0.5, 0.95, 1.0] {
+            let streamed = block_on(
+                rolling_quantile_stream(stream::iter(values.clone()), MEMORY as u32, quantile)
+                    .collect::<Vec<_>>(),
+            );
+            let exact = values
+                .windows(MEMORY)
+                .map(|window| {
+                    let mut sorted = window.to_vec();
+                    sorted.sort_by(f64::total_cmp);
+                    quantile_of_sorted(&sorted, quantile)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(streamed, exact, "quantile {quantile}");
+        }
+    }
+
+    #[test]
+    fn interpolates_between_ranks() {
+        assert_eq!(quantile_of_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.5), 3.0);
+        assert_eq!(quantile_of_sorted(&[1.0, 2.0, 3.0, 4.0], 0.5), 2.5);
+        assert_eq!(quantile_of_sorted(&[10.0, 20.0], 0.25), 12.5);
+        assert_eq!(quantile_of_sorted(&[7.0], 0.9), 7.0);
+    }
+}
@@ -0,0 +1,105 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{returns::LogReturns, util::RollingWindow, Priced};
+
+struct State {
+    log_returns: LogReturns,
+    window: RollingWindow<f64>,
+}
+
+impl State {
+    fn push(&mut self, log_return: f64) -> Option<f64> {
+        self.window.push(log_return);
+        if !self.window.is_full() {
+            return None;
+        }
+
+        let count = self.window.len() as f64;
+        let (second, third) = self.window.iter().fold((0.0, 0.0), |(second, third), r| {
+            (second + r * r, third + r * r * r)
+        });
+
+        // The sums are rebuilt from the window every time, so only a window of zero returns
+        // leaves nothing to scale by
+        if second == 0.0 {
+            Some(0.0)
+        } else {
+            Some(count.sqrt() * third / second.powf(1.5))
+        }
+    }
+}
+
+/// Realized skewness of the log returns over the last `memory` returns,
+/// `sqrt(n) * sum(r^3) / sum(r^2)^(3/2)`.
+///
+/// As is usual for high-frequency returns, the moments are taken around zero rather than the
+/// sample mean (see [`moments_stream`](crate::moments_stream) for the centered skew). Nothing is
+/// emitted until `memory` returns have been seen, and a flat window gives `0.0`.
+pub fn realized_skew_stream<Q, P>(price_stream: Q, memory: u32) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State {
+        log_returns: LogReturns::new(),
+        window: RollingWindow::new(usize::try_from(memory).unwrap()),
+    };
+    price_stream
+        .scan(init, |state, priced| {
+            let skew = state
+                .log_returns
+                .update(priced.price())
+                .and_then(|log_return| state.push(log_return));
+            std::future::ready(Some(skew))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::{price_stream, prices_from_log_returns};
+
+    /// Nine small gains and one crash per ten returns
+    fn crash_returns(scale: f64) -> impl Iterator<Item = f64> {
+        (0..100).map(move |i| {
+            if i % 10 == 0 {
+                -0.09 * scale
+            } else {
+                0.01 * scale
+            }
+        })
+    }
+
+    fn last_skew(prices: &[f64], memory: u32) -> f64 {
+        let skews =
+            block_on(realized_skew_stream(price_stream(prices), memory).collect::<Vec<_>>());
+        *skews.last().unwrap()
+    }
+
+    #[test]
+    fn rare_crashes_skew_left() {
+        let skew = last_skew(&prices_from_log_returns(crash_returns(1.0)), 50);
+        // sqrt(50) * 5 * (-0.09^3 + 9 * 0.01^3) / (5 * (0.09^2 + 9 * 0.01^2))^1.5
+        let expected = 50_f64.sqrt() * 5.0 * -0.00072 / (5.0 * 0.009_f64).powf(1.5);
+        assert!(skew < -1.0, "{skew}");
+        assert!((skew - expected).abs() < 1e-6, "{skew} vs {expected}");
+    }
+
+    #[test]
+    fn tiny_returns_keep_their_skew() {
+        // Far below f64::EPSILON in squared terms, as on a stablecoin pair; skew is scale-free
+        let prices = prices_from_log_returns(crash_returns(1e-9));
+        let skew = last_skew(&prices, 50);
+        let unscaled = last_skew(&prices_from_log_returns(crash_returns(1.0)), 50);
+        assert!((skew - unscaled).abs() < 1e-3, "{skew} vs {unscaled}");
+    }
+
+    #[test]
+    fn flat_window_has_no_skew() {
+        let skews = block_on(realized_skew_stream(price_stream(&[2.0; 8]), 5).collect::<Vec<_>>());
+        assert_eq!(skews, vec![0.0; 3]);
+    }
+}
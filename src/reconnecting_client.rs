@@ -0,0 +1,192 @@
+use std::{future::Future, sync::Arc};
+
+use superchain_client::{
+    config::Config,
+    ethers::types::H160,
+    futures::{
+        future::{self, BoxFuture, Either, FutureExt},
+        stream, Stream, StreamExt, TryStreamExt,
+    },
+    tokio_tungstenite::connect_async,
+    tungstenite::{client::IntoClientRequest, http::header::AUTHORIZATION},
+    Price, WsClient,
+};
+
+use crate::resume::resume_on_error;
+
+const RECONNECTS: usize = 3;
+
+type Backoff = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A [`WsClient`] that opens a new connection whenever the price stream fails.
+#[derive(Clone)]
+pub struct ReconnectingWsClient {
+    url: String,
+    authorization: String,
+    backoff: Backoff,
+}
+
+impl ReconnectingWsClient {
+    /// Connects to `url`, authorizing with the credentials in `config`.
+    pub fn new(url: impl Into<String>, config: &Config) -> Self {
+        Self {
+            url: url.into(),
+            authorization: config.get_basic_authorization_value(),
+            backoff: Arc::new(|| future::ready(()).boxed()),
+        }
+    }
+
+    /// Awaits the future returned by `backoff` before every reconnect, e.g. a timer of the
+    /// caller's runtime. Without one, reconnects are immediate.
+    pub fn with_backoff<F, Fut>(mut self, backoff: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.backoff = Arc::new(move || backoff().boxed());
+        self
+    }
+
+    async fn connect(&self) -> anyhow::Result<WsClient> {
+        let mut req = self.url.as_str().into_client_request()?;
+        req.headers_mut()
+            .append(AUTHORIZATION, self.authorization.as_str().try_into()?);
+        let (websocket, _) = connect_async(req).await?;
+        Ok(WsClient::new(websocket).await)
+    }
+
+    async fn open(
+        self,
+        tokens: Vec<H160>,
+        from: Option<u64>,
+        to_inclusive: Option<u64>,
+    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<Price>> + Send + 'static> {
+        let client = self.connect().await?;
+        let prices = client
+            .get_prices(tokens, from, to_inclusive)
+            .await?
+            .map_err(anyhow::Error::from);
+        // The connection has to stay open for as long as its prices are read.
+        Ok(stream::unfold(
+            (client, prices),
+            |(client, mut prices)| async move {
+                let price = prices.next().await?;
+                Some((price, (client, prices)))
+            },
+        ))
+    }
+
+    /// Requests the prices of `tokens` between the two blocks (inclusive), like
+    /// [`WsClient::get_prices`].
+    ///
+    /// If the stream fails part way, a new connection is opened and the same range is requested
+    /// again, skipping the prices that were already emitted. This relies on the range being
+    /// historical, so that it replays identically. After three reconnects in a row without a new
+    /// price, the error is emitted and the stream ends.
+    pub async fn get_prices(
+        &self,
+        tokens: impl IntoIterator<Item = H160>,
+        from: Option<u64>,
+        to_inclusive: Option<u64>,
+    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<Price>> + Send + Unpin + 'static> {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let first = self
+            .clone()
+            .open(tokens.clone(), from, to_inclusive)
+            .await?;
+        let client = self.clone();
+        let reopen = move || client.clone().open(tokens.clone(), from, to_inclusive);
+        let backoff = Arc::clone(&self.backoff);
+        Ok(reconnecting(first, reopen, move || backoff()).boxed())
+    }
+}
+
+/// Emits `first`, and the stream returned by `reopen` after every failure.
+fn reconnecting<T, S, F, Fut, B, D>(
+    first: S,
+    mut reopen: F,
+    backoff: B,
+) -> impl Stream<Item = anyhow::Result<T>>
+where
+    S: Stream<Item = anyhow::Result<T>>,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<S>>,
+    B: FnMut() -> D,
+    D: Future<Output = ()>,
+{
+    let mut first = Some(first);
+    resume_on_error(
+        move || match first.take() {
+            Some(first) => Either::Left(future::ready(Ok(first))),
+            None => Either::Right(reopen()),
+        },
+        RECONNECTS,
+        backoff,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use superchain_client::futures::{executor::block_on, future::ready};
+
+    use super::*;
+
+    /// A connection that replays `prices`, dropping after `drop_after` of them.
+    fn connection(
+        prices: &[u32],
+        drop_after: Option<usize>,
+    ) -> impl Stream<Item = anyhow::Result<u32>> {
+        let mut replay = prices.iter().map(|&p| Ok(p)).collect::<Vec<_>>();
+        if let Some(n) = drop_after {
+            replay.truncate(n);
+            replay.push(Err(anyhow::anyhow!("connection reset")));
+        }
+        stream::iter(replay)
+    }
+
+    #[test]
+    fn drops_once_and_the_stream_continues() {
+        let prices = [10, 11, 12, 13, 14];
+        let reconnects = Cell::new(0);
+        let backoffs = Cell::new(0);
+        let received = block_on(
+            reconnecting(
+                connection(&prices, Some(2)),
+                || {
+                    reconnects.set(reconnects.get() + 1);
+                    ready(Ok(connection(&prices, None)))
+                },
+                || {
+                    backoffs.set(backoffs.get() + 1);
+                    ready(())
+                },
+            )
+            .map(|price| price.unwrap())
+            .collect::<Vec<_>>(),
+        );
+        assert_eq!(received, prices);
+        assert_eq!(reconnects.get(), 1);
+        assert_eq!(backoffs.get(), 1);
+    }
+
+    #[test]
+    fn gives_up_after_three_reconnects_in_a_row() {
+        let reconnects = Cell::new(0);
+        let received = block_on(
+            reconnecting(
+                connection(&[1, 2], Some(1)),
+                || {
+                    reconnects.set(reconnects.get() + 1);
+                    ready(Ok(connection(&[1, 2], Some(1))))
+                },
+                || ready(()),
+            )
+            .map(|price| price.map_err(|err| err.to_string()))
+            .collect::<Vec<_>>(),
+        );
+        assert_eq!(received, [Ok(1), Err("connection reset".to_owned())]);
+        assert_eq!(reconnects.get(), RECONNECTS);
+    }
+}
@@ -0,0 +1,100 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{ema_stream::Ema, Priced, Volatility};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegimeEvent {
+    Spike,
+    Calm,
+}
+
+struct State {
+    fast: Ema,
+    slow: Ema,
+    threshold: f64,
+    spiking: bool,
+}
+
+impl State {
+    fn new(fast_period: u32, slow_period: u32, threshold: f64) -> Self {
+        Self {
+            fast: Ema::new(fast_period),
+            slow: Ema::new(slow_period),
+            threshold,
+            spiking: false,
+        }
+    }
+}
+
+/// Passes the volatility through, tagged with a [`RegimeEvent`] whenever a fast EMA of it crosses
+/// `threshold` times a slow EMA of it.
+///
+/// [`RegimeEvent::Spike`] fires when the fast average rises above the threshold and
+/// [`RegimeEvent::Calm`] when it falls back below, so each crossing is reported once. Panics if
+/// either period is 0.
+pub fn regime_change_stream<Q, P>(
+    vol_stream: Q,
+    fast_period: u32,
+    slow_period: u32,
+    threshold: f64,
+) -> impl Stream<Item = (f64, Option<RegimeEvent>)> + 'static
+where
+    Q: Stream<Item = Volatility<P>> + 'static,
+    P: Priced + 'static,
+{
+    let init = State::new(fast_period, slow_period, threshold);
+    vol_stream.scan(init, |state, vol| {
+        let fast = state.fast.update(vol.value);
+        let slow = state.slow.update(vol.value);
+
+        let spiking = fast > state.threshold * slow;
+        let event = match (state.spiking, spiking) {
+            (false, true) => Some(RegimeEvent::Spike),
+            (true, false) => Some(RegimeEvent::Calm),
+            _ => None,
+        };
+        state.spiking = spiking;
+
+        std::future::ready(Some((vol.value, event)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::vol_stream;
+
+    #[test]
+    fn spike_fires_when_vol_jumps() {
+        let values = [[0.01; 30].as_slice(), &[0.05; 10]].concat();
+        let tagged =
+            block_on(regime_change_stream(vol_stream(&values), 3, 20, 1.5).collect::<Vec<_>>());
+
+        assert_eq!(tagged.len(), values.len());
+        assert!(tagged
+            .iter()
+            .zip(&values)
+            .all(|((vol, _), value)| vol == value));
+        let events = tagged
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, event))| event.map(|event| (i, event)))
+            .collect::<Vec<_>>();
+        // Once the slow average catches up with a sustained spike it is reported calm again
+        assert_eq!(events.first(), Some(&(30, RegimeEvent::Spike)));
+    }
+
+    #[test]
+    fn calm_follows_when_vol_falls_back() {
+        let values = [[0.01; 30].as_slice(), &[0.05; 3], &[0.01; 30]].concat();
+        let tagged =
+            block_on(regime_change_stream(vol_stream(&values), 3, 20, 1.5).collect::<Vec<_>>());
+        let events = tagged
+            .iter()
+            .filter_map(|(_, event)| *event)
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![RegimeEvent::Spike, RegimeEvent::Calm]);
+    }
+}
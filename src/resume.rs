@@ -0,0 +1,172 @@
+use std::{future::Future, pin::Pin};
+
+use superchain_client::futures::{stream, Stream, StreamExt};
+
+struct State<F, S, B> {
+    open: F,
+    backoff: B,
+    retries: usize,
+    stream: Option<Pin<Box<S>>>,
+    emitted: usize,
+    to_skip: usize,
+    failures: usize,
+    done: bool,
+}
+
+impl<F, S, B> State<F, S, B> {
+    /// Drops the failed stream, and gives the error back once there are no retries left.
+    fn fail(&mut self, err: anyhow::Error) -> Option<anyhow::Error> {
+        self.stream = None;
+        self.failures += 1;
+        if self.failures > self.retries {
+            self.done = true;
+            Some(err)
+        } else {
+            None
+        }
+    }
+}
+
+/// Streams the items of the stream returned by `open`, opening it again if opening it or the
+/// stream itself fails.
+///
+/// A re-opened stream is expected to replay the same items from the start; the ones already
+/// emitted are skipped. `backoff` is awaited before every retry. After `retries` failures in a row
+/// without a new item, the last error is emitted and the stream ends.
+pub(crate) fn resume_on_error<T, F, Fut, S, B, D>(
+    open: F,
+    retries: usize,
+    backoff: B,
+) -> impl Stream<Item = anyhow::Result<T>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<S>>,
+    S: Stream<Item = anyhow::Result<T>>,
+    B: FnMut() -> D,
+    D: Future<Output = ()>,
+{
+    let init = State {
+        open,
+        backoff,
+        retries,
+        stream: None,
+        emitted: 0,
+        to_skip: 0,
+        failures: 0,
+        done: false,
+    };
+    stream::unfold(init, |mut state| async move {
+        while !state.done {
+            let stream = match &mut state.stream {
+                Some(stream) => stream,
+                None => {
+                    if state.failures > 0 {
+                        (state.backoff)().await;
+                    }
+                    match (state.open)().await {
+                        Ok(stream) => {
+                            state.to_skip = state.emitted;
+                            state.stream.insert(Box::pin(stream))
+                        }
+                        Err(err) => match state.fail(err) {
+                            Some(err) => return Some((Err(err), state)),
+                            None => continue,
+                        },
+                    }
+                }
+            };
+            match stream.next().await {
+                Some(Ok(_)) if state.to_skip > 0 => state.to_skip -= 1,
+                Some(Ok(item)) => {
+                    state.emitted += 1;
+                    state.failures = 0;
+                    return Some((Ok(item), state));
+                }
+                Some(Err(err)) => {
+                    if let Some(err) = state.fail(err) {
+                        return Some((Err(err), state));
+                    }
+                }
+                None => return None,
+            }
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, future::ready};
+
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+
+    /// Replays `items`, failing after `fail_after` of them on the first `failing` opens.
+    fn flaky(
+        items: &[u32],
+        fail_after: usize,
+        failing: usize,
+        opens: &Cell<usize>,
+    ) -> impl Stream<Item = anyhow::Result<u32>> {
+        let open = opens.get();
+        opens.set(open + 1);
+        let mut replay = items.iter().map(|&i| Ok(i)).collect::<Vec<_>>();
+        if open < failing {
+            replay.truncate(fail_after);
+            replay.push(Err(anyhow::anyhow!("dropped")));
+        }
+        stream::iter(replay)
+    }
+
+    fn collect<T>(stream: impl Stream<Item = anyhow::Result<T>>) -> Vec<Result<T, String>> {
+        block_on(stream.map(|r| r.map_err(|e| e.to_string())).collect())
+    }
+
+    #[test]
+    fn failure_mid_stream_resumes_without_duplicates() {
+        let opens = Cell::new(0);
+        let items = collect(resume_on_error(
+            || ready(Ok(flaky(&[1, 2, 3, 4, 5], 2, 1, &opens))),
+            1,
+            || ready(()),
+        ));
+        assert_eq!(items, vec![Ok(1), Ok(2), Ok(3), Ok(4), Ok(5)]);
+        assert_eq!(opens.get(), 2);
+    }
+
+    #[test]
+    fn gives_up_after_retries_in_a_row() {
+        let opens = Cell::new(0);
+        let backoffs = Cell::new(0);
+        let items = collect(resume_on_error(
+            || ready(Ok(flaky(&[1, 2, 3], 1, usize::MAX, &opens))),
+            2,
+            || {
+                backoffs.set(backoffs.get() + 1);
+                ready(())
+            },
+        ));
+        assert_eq!(items, vec![Ok(1), Err("dropped".to_owned())]);
+        assert_eq!(opens.get(), 3);
+        assert_eq!(backoffs.get(), 2);
+    }
+
+    #[test]
+    fn failing_open_is_retried() {
+        let opens = Cell::new(0);
+        let items = collect(resume_on_error(
+            || {
+                opens.set(opens.get() + 1);
+                if opens.get() == 1 {
+                    ready(Err(anyhow::anyhow!("refused")))
+                } else {
+                    ready(Ok(stream::iter([Ok(7)])))
+                }
+            },
+            1,
+            || ready(()),
+        ));
+        assert_eq!(items, vec![Ok(7)]);
+    }
+}
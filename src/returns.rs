@@ -0,0 +1,38 @@
+use crate::util::is_usable_price;
+
+/// Turns consecutive prices into log returns, skipping prices a log return can't be taken of.
+pub(crate) struct LogReturns {
+    last_price: Option<f64>,
+}
+
+impl LogReturns {
+    pub(crate) fn new() -> Self {
+        Self { last_price: None }
+    }
+
+    pub(crate) fn update(&mut self, price: f64) -> Option<f64> {
+        if !is_usable_price(price) {
+            return None;
+        }
+        let log_return = self.last_price.map(|last_price| (price / last_price).ln());
+        self.last_price = Some(price);
+        log_return
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_prices_are_skipped_without_breaking_the_chain() {
+        let mut returns = LogReturns::new();
+        let updates = [1.0, 2.0, 0.0, f64::NAN, -1.0, 4.0]
+            .map(|price| returns.update(price))
+            .to_vec();
+        assert_eq!(
+            updates,
+            [None, Some(2f64.ln()), None, None, None, Some(2f64.ln())]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use superchain_client::ethers::types::H160;
+
+/// The Uniswap V2 USDC/WETH pair, 0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc
+const USDC_WETH_PAIR: H160 = H160([
+    180, 225, 109, 1, 104, 229, 45, 53, 202, 205, 44, 97, 133, 180, 66, 129, 236, 40, 201, 220,
+]);
+
+/// Everything a run needs, loaded from a TOML file. Fields left out keep their defaults.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    pub token: H160,
+    pub from_block: Option<u64>,
+    pub to_block_inc: Option<u64>,
+    /// Memory of each volatility series to compute and plot
+    pub windows: Vec<u32>,
+    pub output: PathBuf,
+    pub csv: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            token: USDC_WETH_PAIR,
+            from_block: Some(15500000),
+            to_block_inc: Some(15600000),
+            windows: vec![50, 500, 5000],
+            output: PathBuf::from("vol.svg"),
+            csv: None,
+            width: 1920,
+            height: 1080,
+        }
+    }
+}
+
+impl RunConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.width > 0 && self.height > 0,
+            "graph dimensions must be positive"
+        );
+        anyhow::ensure!(
+            !self.windows.is_empty() && self.windows.iter().all(|&window| window > 0),
+            "at least one volatility window is needed, and windows must be positive"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_full_config() {
+        let run_config: RunConfig = toml::from_str(
+            r#"
+            token = "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc"
+            from_block = 16000000
+            to_block_inc = 16000999
+            windows = [20, 200]
+            output = "out/vol.svg"
+            csv = "out/vol.csv"
+            width = 800
+            height = 600
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(run_config.token, USDC_WETH_PAIR);
+        assert_eq!(run_config.from_block, Some(16_000_000));
+        assert_eq!(run_config.to_block_inc, Some(16_000_999));
+        assert_eq!(run_config.windows, vec![20, 200]);
+        assert_eq!(run_config.output, PathBuf::from("out/vol.svg"));
+        assert_eq!(run_config.csv, Some(PathBuf::from("out/vol.csv")));
+        assert_eq!((run_config.width, run_config.height), (800, 600));
+        run_config.validate().unwrap();
+    }
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let run_config: RunConfig = toml::from_str("windows = [10]").unwrap();
+        let default = RunConfig::default();
+        assert_eq!(run_config.windows, vec![10]);
+        assert_eq!(run_config.token, default.token);
+        assert_eq!(run_config.from_block, default.from_block);
+        assert_eq!(run_config.output, default.output);
+        assert_eq!(run_config.csv, None);
+    }
+
+    #[test]
+    fn unknown_fields_and_bad_values_are_rejected() {
+        assert!(toml::from_str::<RunConfig>("windw = [10]").is_err());
+        assert!(toml::from_str::<RunConfig>("token = \"0x1234\"").is_err());
+        let run_config: RunConfig = toml::from_str("windows = []").unwrap();
+        assert!(run_config.validate().is_err());
+    }
+}
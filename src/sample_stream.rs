@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::Timestamped;
+
+/// Forwards the first item and then every `n`-th one, like `Iterator::step_by`.
+pub fn sample_every_n<Q>(stream: Q, n: usize) -> impl Stream<Item = Q::Item>
+where
+    Q: Stream,
+{
+    assert!(n > 0, "n must be positive");
+    stream
+        .enumerate()
+        .filter_map(move |(index, item)| std::future::ready((index % n == 0).then_some(item)))
+}
+
+/// Forwards the first item and then the first one at least `interval` after the last forwarded
+/// item, going by the items' own timestamps.
+pub fn sample_every<Q>(stream: Q, interval: Duration) -> impl Stream<Item = Q::Item>
+where
+    Q: Stream,
+    Q::Item: Timestamped,
+{
+    let interval = interval.as_secs_f64();
+    let mut last_forwarded = None::<f64>;
+    stream.filter(move |item| {
+        let timestamp = item.timestamp();
+        let forward = match last_forwarded {
+            Some(last) => timestamp - last >= interval,
+            None => true,
+        };
+        if forward {
+            last_forwarded = Some(timestamp);
+        }
+        std::future::ready(forward)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, stream};
+
+    use super::*;
+    use crate::test_util::TestPrice;
+
+    #[test]
+    fn every_third_item_is_forwarded() {
+        let sampled = block_on(sample_every_n(stream::iter(0..10), 3).collect::<Vec<_>>());
+        assert_eq!(sampled, vec![0, 3, 6, 9]);
+    }
+
+    #[test]
+    fn at_most_one_item_per_interval() {
+        let prices = [0.0, 0.4, 1.0, 1.2, 2.5, 2.9, 3.4, 10.0].map(|timestamp| TestPrice {
+            timestamp,
+            price: 1.0,
+        });
+        let sampled = block_on(
+            sample_every(stream::iter(prices), Duration::from_secs(1))
+                .map(|p| p.timestamp)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(sampled, vec![0.0, 1.0, 2.5, 10.0]);
+    }
+}
@@ -0,0 +1,116 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{returns::LogReturns, util::RollingWindow, Priced};
+
+struct State {
+    target: f64,
+    annualizer: f64,
+    log_returns: LogReturns,
+    window: RollingWindow<f64>,
+}
+
+impl State {
+    fn push(&mut self, log_return: f64) -> Option<f64> {
+        self.window.push(log_return);
+
+        if !self.window.is_full() {
+            return None;
+        }
+
+        let count = self.window.len() as f64;
+        let excess = self.window.iter().sum::<f64>() / count - self.target;
+        let downside_variance = self
+            .window
+            .iter()
+            .map(|r| (r - self.target).min(0.0).powi(2))
+            .sum::<f64>()
+            / count;
+
+        let sortino = if downside_variance == 0.0 {
+            // No return fell below the target, so there is no downside risk to scale by
+            if excess > 0.0 {
+                f64::INFINITY
+            } else {
+                0.0
+            }
+        } else {
+            excess / downside_variance.sqrt() * self.annualizer
+        };
+        Some(sortino)
+    }
+}
+
+/// Rolling Sortino ratio of the log returns over the last `memory` returns: the mean return in
+/// excess of `target`, divided by the downside deviation below `target` and annualized by
+/// `sqrt(periods_per_year)`.
+///
+/// Nothing is emitted until `memory` returns have been seen. A window without any return below
+/// `target` gives `f64::INFINITY` if the mean beats the target and `0.0` otherwise.
+pub fn sortino_stream<Q, P>(
+    price_stream: Q,
+    memory: u32,
+    target: f64,
+    periods_per_year: f64,
+) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State {
+        target,
+        annualizer: periods_per_year.sqrt(),
+        log_returns: LogReturns::new(),
+        window: RollingWindow::new(usize::try_from(memory).unwrap()),
+    };
+    price_stream
+        .scan(init, |state, priced| {
+            let sortino = state
+                .log_returns
+                .update(priced.price())
+                .and_then(|log_return| state.push(log_return));
+            std::future::ready(Some(sortino))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::{price_stream, prices_from_log_returns};
+
+    #[test]
+    fn sortino_beats_sharpe_when_losses_are_small() {
+        let log_returns = [0.03, 0.01, -0.01, 0.02].repeat(2);
+        let prices = prices_from_log_returns(log_returns.iter().copied());
+        let sortinos =
+            block_on(sortino_stream(price_stream(&prices), 8, 0.0, 365.0).collect::<Vec<_>>());
+        assert_eq!(sortinos.len(), 1);
+
+        let mean = log_returns.iter().sum::<f64>() / 8.0;
+        let variance = log_returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 8.0;
+        let sharpe = mean / variance.sqrt() * 365_f64.sqrt();
+        // Downside deviation is sqrt(2 * 0.01^2 / 8) = 0.005
+        let expected = mean / 0.005 * 365_f64.sqrt();
+        assert!((sortinos[0] - expected).abs() < 1e-9, "{}", sortinos[0]);
+        assert!(
+            sortinos[0] > sharpe,
+            "sortino {} <= sharpe {sharpe}",
+            sortinos[0]
+        );
+    }
+
+    #[test]
+    fn no_downside_is_infinite_or_zero() {
+        let rising = prices_from_log_returns([0.01; 5]);
+        let sortinos =
+            block_on(sortino_stream(price_stream(&rising), 5, 0.0, 1.0).collect::<Vec<_>>());
+        assert_eq!(sortinos, vec![f64::INFINITY]);
+
+        let flat = [100.0; 6];
+        let sortinos =
+            block_on(sortino_stream(price_stream(&flat), 5, 0.0, 1.0).collect::<Vec<_>>());
+        assert_eq!(sortinos, vec![0.0]);
+    }
+}
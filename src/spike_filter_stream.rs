@@ -0,0 +1,112 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{quantile_stream::RollingQuantile, util::is_usable_price, Priced};
+
+struct State {
+    median: RollingQuantile,
+    multiple: f64,
+}
+
+impl State {
+    fn is_spike(&mut self, price: f64) -> bool {
+        // Written so that zero, negative and NaN prices count as spikes too
+        let spike = match self.median.current() {
+            Some(median) => !(price / median <= self.multiple && median / price <= self.multiple),
+            // Without a median yet, only prices that can't be right are spikes, so that they
+            // don't become the median
+            None => !is_usable_price(price),
+        };
+        if !spike {
+            self.median.push(price);
+        }
+        spike
+    }
+}
+
+/// Tags each item with whether its price is a spike: more than `multiple` times above or below the
+/// median of the last `memory` non-spike prices.
+///
+/// Until `memory` prices have been seen, only zero, negative and non-finite prices are tagged.
+/// Spikes are kept out of the median so a burst of bad prints doesn't drag it along. The flip side
+/// is that a genuine jump by more than `multiple` is flagged for as long as it lasts, so `multiple`
+/// should be well above any plausible move within `memory` prices.
+pub fn spike_tag_stream<Q, P>(
+    price_stream: Q,
+    memory: u32,
+    multiple: f64,
+) -> impl Stream<Item = (P, bool)> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State {
+        median: RollingQuantile::new(memory, 0.5),
+        multiple,
+    };
+    price_stream.scan(init, |state, priced| {
+        let spike = state.is_spike(priced.price());
+        std::future::ready(Some((priced, spike)))
+    })
+}
+
+/// Drops the items [`spike_tag_stream`] tags as spikes.
+pub fn spike_filter_stream<Q, P>(
+    price_stream: Q,
+    memory: u32,
+    multiple: f64,
+) -> impl Stream<Item = P> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    spike_tag_stream(price_stream, memory, multiple)
+        .filter_map(|(priced, spike)| std::future::ready((!spike).then_some(priced)))
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    const PRICES: [f64; 10] = [
+        100.0, 101.0, 99.0, 100.5, 1000.0, 100.2, 0.0, 99.8, 10.0, 100.1,
+    ];
+
+    #[test]
+    fn injected_spikes_are_dropped_and_normal_prices_pass() {
+        let filtered = block_on(
+            spike_filter_stream(price_stream(&PRICES), 3, 5.0)
+                .map(|p| p.price)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            filtered,
+            vec![100.0, 101.0, 99.0, 100.5, 100.2, 99.8, 100.1]
+        );
+    }
+
+    #[test]
+    fn spikes_are_tagged_in_place() {
+        let tagged = block_on(spike_tag_stream(price_stream(&PRICES), 3, 5.0).collect::<Vec<_>>());
+        let spikes = tagged
+            .iter()
+            .filter(|(_, spike)| *spike)
+            .map(|(p, _)| p.timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(tagged.len(), PRICES.len());
+        assert_eq!(spikes, vec![4.0, 6.0, 8.0]);
+    }
+
+    #[test]
+    fn bad_prints_during_warmup_are_dropped() {
+        let prices = [100.0, 0.0, f64::NAN, -1.0, 100.0, 101.0, 99.0, 100.0];
+        let filtered = block_on(
+            spike_filter_stream(price_stream(&prices), 3, 5.0)
+                .map(|p| p.price)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(filtered, vec![100.0, 100.0, 101.0, 99.0, 100.0]);
+    }
+}
@@ -0,0 +1,53 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::Priced;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spread {
+    /// `a / b`
+    Ratio,
+    /// `a - b`
+    Difference,
+}
+
+/// Ratio or difference of two price streams, pairing their items one to one.
+///
+/// The streams are assumed to be aligned: the n-th price of `a` is compared with the n-th price of
+/// `b`, whatever their timestamps. The output ends when either stream ends.
+pub fn spread_stream<QA, PA, QB, PB>(a: QA, b: QB, spread: Spread) -> impl Stream<Item = f64>
+where
+    QA: Stream<Item = PA>,
+    PA: Priced,
+    QB: Stream<Item = PB>,
+    PB: Priced,
+{
+    a.zip(b).map(move |(a, b)| match spread {
+        Spread::Ratio => a.price() / b.price(),
+        Spread::Difference => a.price() - b.price(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    #[test]
+    fn ratio_and_difference_of_aligned_streams() {
+        let a = [10.0, 12.0, 9.0, 15.0];
+        let b = [5.0, 4.0, 3.0];
+
+        let ratios = block_on(
+            spread_stream(price_stream(&a), price_stream(&b), Spread::Ratio).collect::<Vec<_>>(),
+        );
+        assert_eq!(ratios, vec![2.0, 3.0, 3.0]);
+
+        let differences = block_on(
+            spread_stream(price_stream(&a), price_stream(&b), Spread::Difference)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(differences, vec![5.0, 8.0, 6.0]);
+    }
+}
@@ -0,0 +1,72 @@
+use superchain_client::futures::{stream, Stream};
+
+use crate::{Priced, Timestamped, Volatility};
+
+/// A bare price, so the streams can be fed without a client.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TestPrice {
+    pub(crate) timestamp: f64,
+    pub(crate) price: f64,
+}
+
+impl Priced for TestPrice {
+    fn price(&self) -> f64 {
+        self.price
+    }
+}
+
+impl Timestamped for TestPrice {
+    fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+}
+
+/// One price per second, starting at timestamp 0.
+pub(crate) fn price_stream(prices: &[f64]) -> impl Stream<Item = TestPrice> + Unpin + 'static {
+    let prices = prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| TestPrice {
+            timestamp: i as f64,
+            price,
+        })
+        .collect::<Vec<_>>();
+    stream::iter(prices)
+}
+
+/// Prices following the given log returns, starting at 100.
+pub(crate) fn prices_from_log_returns(log_returns: impl IntoIterator<Item = f64>) -> Vec<f64> {
+    let mut prices = vec![100.0];
+    for log_return in log_returns {
+        prices.push(prices.last().unwrap() * f64::exp(log_return));
+    }
+    prices
+}
+
+/// Deterministic values in `[0, 1)` from a small linear congruential generator.
+pub(crate) fn uniform_values(count: usize) -> Vec<f64> {
+    let mut state = 2024_u32;
+    (0..count)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            f64::from(state >> 8) / f64::from(1 << 24)
+        })
+        .collect()
+}
+
+/// Volatilities with the given values, one per second, starting at timestamp 0.
+pub(crate) fn vol_stream(values: &[f64]) -> impl Stream<Item = Volatility<TestPrice>> + 'static {
+    let vols = values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| Volatility {
+            _priced: TestPrice {
+                timestamp: i as f64,
+                price: 1.0,
+            },
+            variance: value * value,
+            value,
+        })
+        .collect::<Vec<_>>();
+    stream::iter(vols)
+}
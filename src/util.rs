@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+/// Whether `price` is one a price stream can use: finite and positive. Zero, negative and NaN
+/// prints are bad data, and would make log returns and ratios of prices meaningless.
+pub(crate) fn is_usable_price(price: f64) -> bool {
+    price.is_finite() && price > 0.0
+}
+
+/// Fixed-capacity window over the most recent values, oldest first.
+#[derive(Debug, Clone)]
+pub struct RollingWindow<T> {
+    capacity: usize,
+    values: VecDeque<T>,
+}
+
+impl<T> RollingWindow<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        Self {
+            capacity,
+            values: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Appends `value`, returning the oldest value if the window was already full.
+    pub fn push(&mut self, value: T) -> Option<T> {
+        let evicted = if self.is_full() {
+            self.values.pop_front()
+        } else {
+            None
+        };
+        self.values.push_back(value);
+        evicted
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.capacity
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.values.iter()
+    }
+}
+
+/// Running mean and population variance of the last `memory` values.
+pub(crate) struct RollingStats {
+    window: RollingWindow<f64>,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl RollingStats {
+    pub(crate) fn new(memory: u32) -> Self {
+        Self {
+            window: RollingWindow::new(usize::try_from(memory).unwrap()),
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    /// Adds `value` to the window and returns `(mean, variance)` once the window is full.
+    ///
+    /// A NaN or infinite value is skipped, returning `None`: evicting it later could not take it
+    /// back out of the running sums. Non-positive values are kept, as returns can be negative;
+    /// streams that push prices check them with [`is_usable_price`] first.
+    pub(crate) fn push(&mut self, value: f64) -> Option<(f64, f64)> {
+        if !value.is_finite() {
+            return None;
+        }
+        if let Some(evicted) = self.window.push(value) {
+            self.sum -= evicted;
+            self.sum_squares -= evicted * evicted;
+        }
+        self.sum += value;
+        self.sum_squares += value * value;
+
+        if !self.window.is_full() {
+            return None;
+        }
+
+        let count = self.window.len() as f64;
+        let mean = self.sum / count;
+        // Cancellation in the running sums can push the variance slightly below zero
+        let variance = (self.sum_squares / count - mean * mean).max(0.0);
+        Some((mean, variance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_oldest_first_once_full() {
+        let mut window = RollingWindow::new(3);
+        assert!(window.is_empty());
+        assert_eq!(window.push(1), None);
+        assert_eq!(window.push(2), None);
+        assert!(!window.is_full());
+        assert_eq!(window.push(3), None);
+        assert!(window.is_full());
+
+        assert_eq!(window.push(4), Some(1));
+        assert_eq!(window.push(5), Some(2));
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn never_grows_past_capacity() {
+        let mut window = RollingWindow::new(2);
+        for value in 0..10 {
+            window.push(value);
+            assert!(window.len() <= 2);
+        }
+        assert_eq!(window.len(), 2);
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![8, 9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be positive")]
+    fn zero_capacity_is_rejected() {
+        RollingWindow::<f64>::new(0);
+    }
+
+    #[test]
+    fn stats_cover_only_the_window() {
+        let mut stats = RollingStats::new(3);
+        assert_eq!(stats.push(1.0), None);
+        assert_eq!(stats.push(2.0), None);
+        for (value, expected_mean, expected_variance) in
+            [(3.0, 2.0, 2.0 / 3.0), (10.0, 5.0, 38.0 / 3.0)]
+        {
+            let (mean, variance) = stats.push(value).unwrap();
+            assert_eq!(mean, expected_mean);
+            assert!((variance - expected_variance).abs() < 1e-12, "{variance}");
+        }
+    }
+
+    #[test]
+    fn only_finite_positive_prices_are_usable() {
+        assert!(is_usable_price(1600.0));
+        assert!(is_usable_price(1e-12));
+        for price in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(!is_usable_price(price), "{price}");
+        }
+    }
+
+    #[test]
+    fn non_finite_values_are_skipped() {
+        let mut stats = RollingStats::new(2);
+        stats.push(1.0);
+        assert_eq!(stats.push(f64::NAN), None);
+        assert_eq!(stats.push(f64::INFINITY), None);
+        assert_eq!(stats.push(3.0), Some((2.0, 1.0)));
+        assert_eq!(stats.push(3.0), Some((3.0, 0.0)));
+    }
+}
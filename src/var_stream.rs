@@ -0,0 +1,57 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{quantile_stream::RollingQuantile, returns::LogReturns, Priced};
+
+struct State {
+    log_returns: LogReturns,
+    quantile: RollingQuantile,
+}
+
+/// Rolling historical Value-at-Risk: the `alpha` quantile (e.g. `0.05`) of the last `memory` log
+/// returns, reported as a loss, so a 2% drop is `0.02`.
+///
+/// Nothing is emitted until `memory` returns have been seen.
+pub fn var_stream<Q, P>(
+    price_stream: Q,
+    memory: u32,
+    alpha: f64,
+) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = State {
+        log_returns: LogReturns::new(),
+        quantile: RollingQuantile::new(memory, alpha),
+    };
+    price_stream
+        .scan(init, |state, priced| {
+            let var = state
+                .log_returns
+                .update(priced.price())
+                .and_then(|log_return| state.quantile.push(log_return))
+                .map(|quantile| -quantile);
+            std::future::ready(Some(var))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::{price_stream, prices_from_log_returns};
+
+    #[test]
+    fn var_is_the_empirical_loss_quantile() {
+        // Log returns of -10% to +9% in 1% steps, out of order
+        let prices = prices_from_log_returns((0..20).map(|i| f64::from((i * 7) % 20 - 10) / 100.0));
+
+        let vars = block_on(var_stream(price_stream(&prices), 20, 0.05).collect::<Vec<_>>());
+        assert_eq!(vars.len(), 1);
+        // The 5% quantile sits 0.95 of the way from the -10% to the -9% return
+        let expected = 0.10 - 0.01 * 0.95;
+        assert!((vars[0] - expected).abs() < 1e-12, "{}", vars[0]);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::quantile_stream::quantile_of_sorted;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolConeRow {
+    pub horizon: usize,
+    pub min: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub max: f64,
+}
+
+fn realized_vol(returns: &[f64]) -> f64 {
+    let count = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / count;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / count;
+    variance.sqrt()
+}
+
+/// Distribution of the realized volatility of `returns` over every overlapping window of each
+/// horizon, one row per horizon.
+///
+/// Volatilities are per return period, not annualized. Horizons of zero or longer than `returns`
+/// have no window and are left out.
+pub fn vol_cone(returns: &[f64], horizons: &[usize]) -> Vec<VolConeRow> {
+    horizons
+        .iter()
+        .filter(|&&horizon| horizon > 0 && horizon <= returns.len())
+        .map(|&horizon| {
+            let mut vols = returns
+                .windows(horizon)
+                .map(realized_vol)
+                .collect::<Vec<_>>();
+            vols.sort_by(f64::total_cmp);
+            VolConeRow {
+                horizon,
+                min: vols[0],
+                p25: quantile_of_sorted(&vols, 0.25),
+                median: quantile_of_sorted(&vols, 0.5),
+                p75: quantile_of_sorted(&vols, 0.75),
+                max: vols[vols.len() - 1],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::uniform_values;
+
+    /// Deterministic returns in `[-0.02, 0.02)`.
+    fn returns(count: usize) -> Vec<f64> {
+        uniform_values(count)
+            .into_iter()
+            .map(|value| (value - 0.5) * 0.04)
+            .collect()
+    }
+
+    #[test]
+    fn cone_widens_at_shorter_horizons() {
+        let cone = vol_cone(&returns(500), &[5, 20, 100]);
+        assert_eq!(
+            cone.iter().map(|row| row.horizon).collect::<Vec<_>>(),
+            vec![5, 20, 100]
+        );
+        for row in &cone {
+            assert!(row.min <= row.p25 && row.p25 <= row.median, "{row:?}");
+            assert!(row.median <= row.p75 && row.p75 <= row.max, "{row:?}");
+        }
+        for pair in cone.windows(2) {
+            let (short, long) = (pair[0], pair[1]);
+            assert!(
+                short.max - short.min > long.max - long.min,
+                "{short:?} vs {long:?}"
+            );
+            assert!(
+                short.p75 - short.p25 > long.p75 - long.p25,
+                "{short:?} vs {long:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn horizons_without_a_window_are_left_out() {
+        let cone = vol_cone(&returns(10), &[0, 3, 10, 11]);
+        assert_eq!(
+            cone.iter().map(|row| row.horizon).collect::<Vec<_>>(),
+            vec![3, 10]
+        );
+        assert_eq!(cone[1].min, cone[1].max);
+    }
+}
@@ -0,0 +1,52 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{util::RollingStats, Priced, Volatility};
+
+/// Volatility of volatility: the rolling standard deviation of the values of a volatility stream
+/// over the last `memory` of them.
+///
+/// Nothing is emitted until `memory` volatilities have been seen. NaN and infinite volatilities are
+/// skipped.
+pub fn vol_of_vol_stream<Q, P>(vol_stream: Q, memory: u32) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = Volatility<P>> + 'static,
+    P: Priced + 'static,
+{
+    let init = RollingStats::new(memory);
+    vol_stream
+        .scan(init, |stats, vol| {
+            let vol_of_vol = stats
+                .push(vol.value)
+                .map(|(_mean, variance)| variance.sqrt());
+            std::future::ready(Some(vol_of_vol))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::executor::block_on;
+
+    use super::*;
+    use crate::test_util::vol_stream;
+
+    #[test]
+    fn oscillating_vol_has_positive_finite_vol_of_vol() {
+        let values = (0..40)
+            .map(|i| if i % 2 == 0 { 0.3 } else { 0.5 })
+            .collect::<Vec<_>>();
+        let vol_of_vols = block_on(vol_of_vol_stream(vol_stream(&values), 10).collect::<Vec<_>>());
+
+        assert_eq!(vol_of_vols.len(), 31);
+        for vol_of_vol in vol_of_vols {
+            assert!(vol_of_vol.is_finite() && vol_of_vol > 0.0);
+            assert!((vol_of_vol - 0.1).abs() < 1e-9, "{vol_of_vol}");
+        }
+    }
+
+    #[test]
+    fn constant_vol_has_no_vol_of_vol() {
+        let vol_of_vols = block_on(vol_of_vol_stream(vol_stream(&[0.2; 8]), 4).collect::<Vec<_>>());
+        assert!(vol_of_vols.iter().all(|&vol_of_vol| vol_of_vol < 1e-9));
+    }
+}
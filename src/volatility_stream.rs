@@ -1,6 +1,9 @@
 use superchain_client::futures::{Stream, StreamExt};
 
-use crate::{Priced, Volatility};
+use crate::{
+    util::{is_usable_price, RollingStats},
+    Priced, Volatility,
+};
 
 struct State {
     memory: u32,
@@ -31,24 +34,111 @@ where
     let init = State::new(memory);
     price_stream.scan(init, |state, priced| {
         let price = priced.price();
+        // A bad price still gets a volatility, the last estimate, so there is one per price
+        if is_usable_price(price) {
+            let nominator1 =
+                f64::try_from(u32::from(state.memory - 1) * u32::from(state.count)).unwrap();
+            let denominator =
+                f64::try_from(u32::from(state.memory + 1) * u32::from(state.count + 1)).unwrap();
+            let nominator2 = 4f64;
 
-        let nominator1 =
-            f64::try_from(u32::from(state.memory - 1) * u32::from(state.count)).unwrap();
-        let denominator =
-            f64::try_from(u32::from(state.memory + 1) * u32::from(state.count + 1)).unwrap();
-        let nominator2 = 4f64;
-
-        let variance = (nominator1 * state.last_variance + nominator2 * price * state.sum_prices)
-            / denominator;
-
-        state.sum_prices += price;
-        state.count += 1;
-        state.last_variance = variance;
+            state.last_variance = (nominator1 * state.last_variance
+                + nominator2 * price * state.sum_prices)
+                / denominator;
+            state.sum_prices += price;
+            state.count += 1;
+        }
 
+        let variance = state.last_variance;
         let vol = Volatility::<P> {
             _priced: priced,
+            variance,
             value: variance.sqrt(),
         };
         std::future::ready(Some(vol))
     })
 }
+
+/// Rolling volatility (standard deviation) of a stream of returns over the last `memory` returns,
+/// for when returns are already at hand, e.g. from a different price source.
+///
+/// Nothing is emitted until `memory` returns have been seen. NaN and infinite returns are skipped.
+pub fn volatility_from_returns<Q>(
+    returns_stream: Q,
+    memory: u32,
+) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = f64> + 'static,
+{
+    let init = RollingStats::new(memory);
+    returns_stream
+        .scan(init, |stats, value| {
+            let vol = stats.push(value).map(|(_mean, variance)| variance.sqrt());
+            std::future::ready(Some(vol))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, stream};
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    #[test]
+    fn value_is_the_square_root_of_variance() {
+        let prices = [1.0, 1.1, 0.9, 1.3, 1.2, 0.8, 1.0];
+        let vols = block_on(volatility_stream(price_stream(&prices), 5).collect::<Vec<_>>());
+
+        assert_eq!(vols.len(), prices.len());
+        assert!(vols.iter().skip(1).any(|vol| vol.variance > 0.0));
+        for vol in &vols {
+            assert_eq!(vol.value, vol.variance.sqrt());
+        }
+    }
+
+    #[test]
+    fn vol_from_returns_matches_their_std() {
+        // Alternating +2% / -2% has a mean of zero and a standard deviation of exactly 2%
+        let returns = (0..30).map(|i| if i % 2 == 0 { 0.02 } else { -0.02 });
+        let vols = block_on(volatility_from_returns(stream::iter(returns), 10).collect::<Vec<_>>());
+
+        assert_eq!(vols.len(), 21);
+        for vol in vols {
+            assert!((vol - 0.02).abs() < 1e-12, "{vol}");
+        }
+    }
+
+    #[test]
+    fn vol_from_returns_recovers_after_an_infinite_return() {
+        let returns = [0.02, -0.02, f64::INFINITY, 0.02, -0.02, 0.02];
+        let vols = block_on(volatility_from_returns(stream::iter(returns), 2).collect::<Vec<_>>());
+        assert_eq!(vols.len(), 4);
+        for vol in vols {
+            assert!((vol - 0.02).abs() < 1e-12, "{vol}");
+        }
+    }
+
+    #[test]
+    fn unusable_prices_repeat_the_last_estimate() {
+        let clean = [1.0, 1.1, 0.9, 1.3, 1.2];
+        let dirty = [1.0, 1.1, f64::NAN, 0.9, 0.0, 1.3, -1.0, 1.2];
+        let variances = |prices: &[f64]| {
+            block_on(
+                volatility_stream(price_stream(prices), 5)
+                    .map(|vol| vol.variance)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let (clean, dirty) = (variances(&clean), variances(&dirty));
+
+        assert_eq!(dirty.len(), 8);
+        assert_eq!(
+            [dirty[2], dirty[4], dirty[6]],
+            [clean[1], clean[2], clean[3]]
+        );
+        let usable = [0, 1, 3, 5, 7].map(|i| dirty[i]);
+        assert_eq!(usable.to_vec(), clean);
+    }
+}
@@ -0,0 +1,38 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{Priced, Volatility};
+
+/// Variance risk premium proxy: implied minus realized volatility, per observation.
+///
+/// The two streams are assumed to be aligned: the n-th implied volatility is compared with the
+/// n-th realized one, so both must be sampled at the same points in time and expressed in the same
+/// units (e.g. both annualized). The output ends when either stream ends.
+pub fn vrp_stream<QR, P, QI>(
+    realized_vol_stream: QR,
+    implied_vol_stream: QI,
+) -> impl Stream<Item = f64>
+where
+    QR: Stream<Item = Volatility<P>>,
+    P: Priced,
+    QI: Stream<Item = f64>,
+{
+    realized_vol_stream
+        .zip(implied_vol_stream)
+        .map(|(realized, implied)| implied - realized.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, stream};
+
+    use super::*;
+    use crate::test_util::vol_stream;
+
+    #[test]
+    fn constant_vols_give_constant_spread() {
+        let realized = vol_stream(&[0.25; 10]);
+        let implied = stream::iter([0.75; 8]);
+        let spreads = block_on(vrp_stream(realized, implied).collect::<Vec<_>>());
+        assert_eq!(spreads, vec![0.5; 8]);
+    }
+}
@@ -0,0 +1,65 @@
+use superchain_client::futures::{Stream, StreamExt};
+
+use crate::{
+    util::{is_usable_price, RollingStats},
+    Priced,
+};
+
+/// Rolling z-score `(price - mean) / std` over the last `memory` prices.
+///
+/// Nothing is emitted until `memory` prices have been seen, and a flat window yields `0.0`.
+/// Non-positive and non-finite prices are skipped.
+pub fn zscore_stream<Q, P>(price_stream: Q, memory: u32) -> impl Stream<Item = f64> + 'static
+where
+    Q: Stream<Item = P> + Unpin + 'static,
+    P: Priced + 'static,
+{
+    let init = RollingStats::new(memory);
+    price_stream
+        .scan(init, |stats, priced| {
+            let price = priced.price();
+            if !is_usable_price(price) {
+                return std::future::ready(Some(None));
+            }
+            let zscore = stats.push(price).map(|(mean, variance)| {
+                let std = variance.sqrt();
+                if std == 0.0 {
+                    0.0
+                } else {
+                    (price - mean) / std
+                }
+            });
+            std::future::ready(Some(zscore))
+        })
+        .filter_map(std::future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use superchain_client::futures::{executor::block_on, StreamExt};
+
+    use super::*;
+    use crate::test_util::price_stream;
+
+    #[test]
+    fn price_far_above_mean_gives_large_positive_zscore() {
+        let prices = [10.0, 10.1, 9.9, 10.0, 10.05, 20.0];
+        let zscores = block_on(zscore_stream(price_stream(&prices), 5).collect::<Vec<_>>());
+        assert_eq!(zscores.len(), 2);
+        assert!(zscores[0].abs() < 1.0);
+        assert!(zscores[1] > 1.5, "{}", zscores[1]);
+    }
+
+    #[test]
+    fn flat_window_gives_zero() {
+        let zscores = block_on(zscore_stream(price_stream(&[3.0; 6]), 4).collect::<Vec<_>>());
+        assert_eq!(zscores, vec![0.0; 3]);
+    }
+
+    #[test]
+    fn recovers_after_a_nan_price() {
+        let prices = [10.0, 11.0, f64::NAN, 10.0, 0.0, 11.0, 10.0, 11.0];
+        let zscores = block_on(zscore_stream(price_stream(&prices), 2).collect::<Vec<_>>());
+        assert_eq!(zscores, vec![1.0, -1.0, 1.0, -1.0, 1.0]);
+    }
+}